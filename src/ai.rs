@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::game_state::GameState;
+use crate::opening_book;
+use crate::player::Player;
+
+const WIN_SCORE: i32 = 1_000_000;
+
+/// What the search knows of a score it stored, alpha-beta cuts leave only a bound
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+enum Bound {
+    Exact,
+    /// The score is at least this
+    Lower,
+    /// The score is at most this
+    Upper,
+}
+
+/// A position the search has scored, found again when other move orders lead to it
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+struct TableEntry {
+    depth: u32,
+    score: i32,
+    bound: Bound,
+}
+
+/// Transposition table by `Game::key`, one per search since scores are for the player the search is for
+type Table = HashMap<u64, TableEntry>;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn depth(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 2,
+            Difficulty::Medium => 4,
+            Difficulty::Hard => 6,
+        }
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Difficulty, String> {
+        Self::ALL.iter()
+            .find(|difficulty| difficulty.text() == text)
+            .copied()
+            .ok_or(format!("Unknown difficulty '{text}'"))
+    }
+}
+
+/// Plays from the opening book on hard while the position is in it, otherwise
+/// searches with minimax, `difficulty` sets the search depth.
+/// With more than two players every opponent is assumed to play against the AI.
+pub fn best_move(game: &Game, difficulty: Difficulty) -> Option<Move> {
+    // The book is solved, the easier difficulties would play above their level
+    if difficulty == Difficulty::Hard {
+        if let Some(mv) = opening_book::book().best_move(game) {
+            return Some(mv);
+        }
+    }
+
+    search(game, search_depth(game, difficulty)).map(|(mv, _)| mv)
+}
+
+/// How deep the difficulty searches the game
+fn search_depth(game: &Game, difficulty: Difficulty) -> u32 {
+    // Without gravity every empty cell is a move, the tree gets too wide for the full depth
+    if game.gravity { difficulty.depth() } else { difficulty.depth() / 2 + 1 }
+}
+
+/// The score of every legal move for the player to move, searched as deep as the difficulty searches
+pub fn move_scores(game: &Game, difficulty: Difficulty) -> Vec<(Move, i32)> {
+    let GameState::Running(player) = game.state else { return vec![]; };
+
+    let depth = search_depth(game, difficulty);
+    let mut game = game.clone();
+    let mut table = Table::new();
+
+    game.legal_moves().into_iter()
+        .map(|mv| {
+            game.apply(mv);
+            let score = minimax(&mut game, depth - 1, -WIN_SCORE * 2, WIN_SCORE * 2, player, &mut table);
+            game.undo();
+
+            (mv, score)
+        })
+        .collect()
+}
+
+/// A score as `+15`, or `Win` and `Loss` once the search sees the end of the game
+pub fn score_text(score: i32) -> String {
+    match score {
+        score if score > WIN_SCORE / 2 => String::from("Win"),
+        score if score < -WIN_SCORE / 2 => String::from("Loss"),
+        score => format!("{score:+}"),
+    }
+}
+
+/// Minimax search with alpha-beta pruning, the best move and its score for the player to move
+pub fn search(game: &Game, depth: u32) -> Option<(Move, i32)> {
+    let GameState::Running(player) = game.state else { return None; };
+
+    let mut best = None;
+    let mut alpha = -WIN_SCORE * 2;
+
+    // Moves are played and taken back on one copy instead of cloning the game for every node
+    let mut game = game.clone();
+    let mut table = Table::new();
+
+    for mv in ordered_moves(&game) {
+        game.apply(mv);
+        let score = minimax(&mut game, depth - 1, alpha, WIN_SCORE * 2, player, &mut table);
+        game.undo();
+
+        if best.is_none() || score > alpha {
+            alpha = score;
+            best = Some(mv);
+        }
+    }
+
+    best.map(|mv| (mv, alpha))
+}
+
+/// Scores the game for `player`, who maximizes while everyone else minimizes
+fn minimax(game: &mut Game, depth: u32, mut alpha: i32, mut beta: i32, player: Player, table: &mut Table) -> i32 {
+    match game.state {
+        // Prefer quick wins and slow losses
+        GameState::Win { player: winner, .. } if winner == player => return WIN_SCORE + depth as i32,
+        GameState::Win { .. } => return -WIN_SCORE - depth as i32,
+        GameState::Draw(_) => return 0,
+        _ => ()
+    }
+
+    // Either side can repeat the position forever, so it counts as a draw. Its score depends on
+    // the way there, so it isn't stored.
+    if game.repetitions() > 0 {
+        return 0;
+    }
+
+    if depth == 0 {
+        return game.rules().score(game, player);
+    }
+
+    // Only entries of the same depth are used, quick wins score higher so a deeper search scores differently
+    let key = game.key();
+
+    if let Some(entry) = table.get(&key).filter(|entry| entry.depth == depth) {
+        match entry.bound {
+            Bound::Exact => return entry.score,
+            Bound::Lower if entry.score >= beta => return entry.score,
+            Bound::Upper if entry.score <= alpha => return entry.score,
+            _ => ()
+        }
+    }
+
+    let window = (alpha, beta);
+
+    // The player to move again after an x2 coin keeps maximizing
+    let maximizing = matches!(game.state, GameState::Running(mover) if mover == player);
+
+    // Power coins are only tried at the root, they would multiply the size of the tree
+    let mut moves: Vec<Move> = ordered_moves(game).into_iter()
+        .filter(|mv| !matches!(mv, Move::Power(..)))
+        .collect();
+
+    if moves.is_empty() {
+        moves = ordered_moves(game);
+    }
+
+    for mv in moves {
+        game.apply(mv);
+        let score = minimax(game, depth - 1, alpha, beta, player, table);
+        game.undo();
+
+        if maximizing {
+            alpha = alpha.max(score);
+        } else {
+            beta = beta.min(score);
+        }
+
+        if alpha >= beta {
+            break;
+        }
+    }
+
+    let score = if maximizing { alpha } else { beta };
+
+    let bound = match window {
+        (alpha, _) if score <= alpha => Bound::Upper,
+        (_, beta) if score >= beta => Bound::Lower,
+        _ => Bound::Exact,
+    };
+
+    table.insert(key, TableEntry { depth, score, bound });
+
+    score
+}
+
+/// Center columns first, they take part in the most lines, and center rows for placements
+fn ordered_moves(game: &Game) -> Vec<Move> {
+    let center = game.columns() as i32 / 2;
+    let middle = game.rows() as i32 / 2;
+    let mut moves = game.legal_moves();
+
+    moves.sort_by_key(|mv| {
+        let row_distance = match mv {
+            Move::Place(_, row) => (*row as i32 - middle).abs(),
+            _ => 0,
+        };
+
+        let column_distance = mv.column().map_or(0, |col| (col as i32 - center).abs());
+
+        (!matches!(mv, Move::Drop(_) | Move::Place(..)), column_distance + row_distance)
+    });
+
+    moves
+}
@@ -1,17 +1,42 @@
+use std::collections::{BTreeMap, HashMap};
+
+use arboard::Clipboard;
 use gfx_device_gl::Device;
 use graphics::Context;
-use piston::{RenderArgs, UpdateArgs};
+use graphics::types::Matrix2d;
+use piston::{Key, RenderArgs, UpdateArgs};
 use piston_window::{G2d, Glyphs, G2dTexture};
 
 use crate::animation::animatable::Animatable;
 use crate::constants::colors::{DARK_BLUE, LIGHT_BLUE};
 use crate::player::Player;
 use crate::{Pos, Size};
+use crate::ai::{self, Difficulty};
+use crate::rating;
 use crate::animation::Animation;
-use crate::constants::{COLUMNS, ROWS};
+use crate::bomb_state::BombState;
+use crate::clock::{Clock, TimeControl};
+use crate::constants::{EXPLORER_WIDTH, MATCH_LENGTHS, TIME_CONTROLS};
+use crate::database::GameDatabase;
+use crate::editor::Editor;
+use crate::explorer::{Explorer, ExplorerRow};
 use crate::game::Game;
-use crate::game_state::GameState;
+use crate::game_match::Match;
+use crate::game_move::Move;
+use crate::game_state::{DrawReason, GameState, WinReason};
 use crate::gravity_floor_state::GravityFloorState;
+use crate::options::Options;
+use crate::participant::Participant;
+use crate::piece::PieceKind;
+use crate::pop_state::PopState;
+use crate::profile::{GameRecord, ProfileStore};
+use crate::puzzle::Puzzle;
+use crate::rules::{self, rotation};
+use crate::scale_in_state::ScaleInState;
+use crate::screen::Screen;
+use crate::seat::Seat;
+use crate::start_policy::StartPolicy;
+use crate::variant::Variant;
 
 pub struct App {
     game: Game,
@@ -19,31 +44,103 @@ pub struct App {
     font: Glyphs,
     tile: G2dTexture,
     tile_bg: G2dTexture,
-    coin_red: G2dTexture,
-    coin_yellow: G2dTexture,
-    arrow_yellow: G2dTexture,
-    arrow_red: G2dTexture,
+    coins: HashMap<Player, G2dTexture>,
+    arrows: HashMap<Player, G2dTexture>,
     mouse_pos: Pos,
     animations: Vec<Box<dyn Animatable>>,
+    time_control: Option<TimeControl>,
+    clock: Option<Clock>,
+    best_of: Option<u32>,
+    game_match: Option<Match>,
+    result_recorded: bool,
+    screen: Screen,
+    participants: BTreeMap<Seat, Participant>,
+    start_policy: StartPolicy,
+    first_player: Player,
+    /// Who started the last game and who won it
+    last_game: Option<(Player, Option<Player>)>,
+    profiles: ProfileStore,
+    selected_profile: usize,
+    variant: Variant,
+    /// Columns whose coins are being animated
+    hidden_columns: Vec<usize>,
+    /// The power coin the next click drops instead of a normal coin
+    selected_power: Option<PieceKind>,
+    /// Off lets the next games place coins on any empty cell, if the variant allows it
+    gravity: bool,
+    puzzles: Vec<Puzzle>,
+    /// The puzzle being played, as an index into `puzzles`
+    puzzle: Option<usize>,
+    database: GameDatabase,
+    /// The opening explorer beside the board, while it is open
+    explorer: Option<Explorer>,
+    /// Whether the cells that would complete a line are marked
+    threats: bool,
+    /// The position editor, while a position is being set up
+    editor: Option<Editor>,
+    /// Opened the first time a position is copied or pasted, it has to stay open
+    /// for what was copied to stay on the clipboard
+    clipboard: Option<Clipboard>,
 }
 
 impl App {
-    pub fn initial(font: Glyphs, tile: G2dTexture, tile_bg: G2dTexture, coin_yellow: G2dTexture, coin_red: G2dTexture, arrow_yellow: G2dTexture, arrow_red: G2dTexture) -> App {
+    pub fn initial(font: Glyphs, tile: G2dTexture, tile_bg: G2dTexture, coins: HashMap<Player, G2dTexture>, arrows: HashMap<Player, G2dTexture>) -> App {
+        let options = Options::default();
+
         Self {
-            game: Game::initial(),
+            game: Game::initial(Variant::Classic, Seat::solo(&[Player::Yellow, Player::Red])),
             window_size: (0.0, 0.0),
             mouse_pos: (0.0, 0.0),
             font,
             tile,
             tile_bg,
-            coin_yellow,
-            coin_red,
-            arrow_yellow,
-            arrow_red,
+            coins,
+            arrows,
             animations: vec![],
+            time_control: None,
+            clock: None,
+            best_of: None,
+            game_match: None,
+            result_recorded: false,
+            screen: Screen::Board,
+            participants: BTreeMap::from([(Seat::new(Player::Yellow, 0), options.yellow), (Seat::new(Player::Red, 0), options.red)]),
+            start_policy: options.start_policy,
+            first_player: Player::Yellow,
+            last_game: None,
+            profiles: ProfileStore::default(),
+            selected_profile: 0,
+            variant: Variant::Classic,
+            hidden_columns: vec![],
+            selected_power: None,
+            gravity: true,
+            puzzles: vec![],
+            puzzle: None,
+            database: GameDatabase::default(),
+            explorer: None,
+            threats: false,
+            editor: None,
+            clipboard: None,
         }
     }
 
+    pub fn with_options(mut self, options: Options) -> App {
+        self.participants = BTreeMap::from([(Seat::new(Player::Yellow, 0), options.yellow), (Seat::new(Player::Red, 0), options.red)]);
+        self.participants.extend(options.green.map(|green| (Seat::new(Player::Green, 0), green)));
+        self.participants.extend(options.blue.map(|blue| (Seat::new(Player::Blue, 0), blue)));
+        self.participants.extend(options.yellow_partner.map(|partner| (Seat::new(Player::Yellow, 1), partner)));
+        self.participants.extend(options.red_partner.map(|partner| (Seat::new(Player::Red, 1), partner)));
+        self.start_policy = options.start_policy;
+        self.profiles = ProfileStore::open(&options.data_dir);
+        self.database = GameDatabase::open(&options.data_dir);
+        self.reset();
+        self
+    }
+
+    pub fn with_puzzles(mut self, puzzles: Vec<Puzzle>) -> App {
+        self.puzzles = puzzles;
+        self
+    }
+
     pub fn render(&mut self,
                   args: &RenderArgs,
                   c: Context,
@@ -55,46 +152,181 @@ impl App {
 
         self.window_size = (args.window_size[0], args.window_size[1]);
 
-        let ((offset_x, offset_y), (board_size, _)) = self.get_dimensions();
+        match self.screen {
+            Screen::Board => {
+                self.render_board(c, gl, d);
+                self.render_explorer(c, gl, d);
+            }
+            Screen::MatchSummary => self.render_match_summary(c, gl, d),
+            Screen::Statistics => self.render_statistics(c, gl, d),
+            Screen::Leaderboard => self.render_leaderboard(c, gl, d),
+            Screen::VariantMenu => self.render_variant_menu(c, gl, d),
+        }
+    }
+
+    fn render_board(&mut self, c: Context, gl: &mut G2d, d: &mut Device) {
+        use graphics::*;
 
-        let col_width = board_size / COLUMNS as f64;
+        let ((offset_x, offset_y), (board_width, board_height)) = self.get_dimensions();
+
+        let col_width = board_width / self.game.columns() as f64;
 
         let hover_column = self.get_mouse_column();
+        let hover_cell = self.get_mouse_cell();
 
         let t_matrix = c.transform.trans(offset_x, offset_y);
         
-        for col in 0..COLUMNS {
+        for col in 0..self.game.columns() {
             let x = col as f64 * col_width;
-            for row in 0..ROWS {
-                let y = board_size - row as f64 * col_width - col_width;
+            for row in 0..self.game.rows() {
+                let y = board_height - row as f64 * col_width - col_width;
                 
                 image(&self.tile_bg, t_matrix.trans(x, y).scale(col_width / 400.0, col_width / 400.0), gl);
             }
         }
 
+        // Threats, filled on odd rows and hollow on even rows, ringed if the next move can fill them
+        if self.threats && self.puzzle.is_none() {
+            for threat in rules::threats(&self.game) {
+                let size = if threat.immediate { col_width * 0.4 } else { col_width * 0.25 };
+                let x = threat.col as f64 * col_width + (col_width - size) / 2.0;
+                let y = board_height - threat.row as f64 * col_width - col_width + (col_width - size) / 2.0;
+
+                if threat.immediate {
+                    let ring = size * 0.15;
+                    ellipse(color::BLACK, [x - ring, y - ring, size + ring * 2.0, size + ring * 2.0], t_matrix, gl);
+                }
+
+                if threat.is_odd() {
+                    ellipse(threat.player.color(), [x, y, size, size], t_matrix, gl);
+                } else {
+                    let border = size * 0.08;
+                    circle_arc(threat.player.color(), border, 0.0, std::f64::consts::TAU,
+                               [x + border, y + border, size - border * 2.0, size - border * 2.0], t_matrix, gl);
+                }
+            }
+        }
+
+        // Captured piles beside the board
+        if self.game.rules().captures_to_win().is_some() {
+            for player in self.game.players.clone() {
+                let (x, top, height) = self.side_panel(player);
+                let coin_size = (col_width * 0.6).min(height / 5.0);
+                let count = self.game.captured(player);
+                let coin = self.coin(player);
+
+                for i in 0..count {
+                    let y = top + height - coin_size - i as f64 * coin_size * 0.3;
+                    image(&coin, t_matrix.trans(x - coin_size / 2.0, y).scale(coin_size / 400.0, coin_size / 400.0), gl);
+                }
+
+                let label = count.to_string();
+                let label_width = self.font.width(coin_size as u32 / 2, &label).unwrap_or(0.0);
+                let label_y = top + height - coin_size - count as f64 * coin_size * 0.3 - coin_size * 0.2;
+
+                text::Text::new_color(color::BLACK, coin_size as u32 / 2)
+                    .draw(&label,
+                          &mut self.font,
+                          &c.draw_state,
+                          t_matrix.trans(x - label_width / 2.0, label_y),
+                          gl).unwrap();
+            }
+        }
+
+        // Power coins beside the board, used ones faded
+        if !self.game.rules().powers().is_empty() {
+            for player in self.game.players.clone() {
+                let (x, top, height) = self.side_panel(player);
+                let coin_size = (col_width * 0.6).min(height / 7.0);
+                let coin = self.coin(player);
+                let to_move = matches!(self.game.state, GameState::Running(p) if p == player);
+
+                for (i, kind) in PieceKind::POWERS.into_iter().enumerate() {
+                    let y = top + coin_size * 0.5 + i as f64 * coin_size * 1.6;
+
+                    if to_move && self.selected_power == Some(kind) {
+                        ellipse(color::BLACK, [x - coin_size * 0.6, y - coin_size * 0.1, coin_size * 1.2, coin_size * 1.2], t_matrix, gl);
+                    }
+
+                    draw_piece(&coin, kind, coin_size, t_matrix.trans(x - coin_size / 2.0, y), gl);
+
+                    if !self.game.powers(player).contains(&kind) {
+                        ellipse([0.5, 0.5, 0.5, 0.7], [x - coin_size / 2.0, y, coin_size, coin_size], t_matrix, gl);
+                    }
+
+                    let label = format!("{} {}", i + 1, kind.text());
+                    let label_width = self.font.width(coin_size as u32 / 4, &label).unwrap_or(0.0);
+
+                    text::Text::new_color(color::BLACK, coin_size as u32 / 4)
+                        .draw(&label,
+                              &mut self.font,
+                              &c.draw_state,
+                              t_matrix.trans(x - label_width / 2.0, y + coin_size * 1.25),
+                              gl).unwrap();
+                }
+            }
+        }
+
+        // Rotations left beside the board
+        if self.game.rules().rotations() > 0 {
+            for player in self.game.players.clone() {
+                let (x, top, height) = self.side_panel(player);
+                let coin_size = (col_width * 0.6).min(height / 3.0);
+                let y = top + (height - coin_size) / 2.0;
+
+                image(&self.coin(player), t_matrix.trans(x - coin_size / 2.0, y).scale(coin_size / 400.0, coin_size / 400.0), gl);
+
+                let label = format!("{} turns", self.game.rotations(player));
+                let label_width = self.font.width(coin_size as u32 / 4, &label).unwrap_or(0.0);
+
+                text::Text::new_color(color::BLACK, coin_size as u32 / 4)
+                    .draw(&label,
+                          &mut self.font,
+                          &c.draw_state,
+                          t_matrix.trans(x - label_width / 2.0, y + coin_size * 1.25),
+                          gl).unwrap();
+            }
+        }
+
+        // Ghost columns, the opposite edge of a wrapping board repeated beside it
+        if self.game.rules().wraps() {
+            let last = self.game.columns() - 1;
+
+            for (col, x) in [(last, -col_width), (0, board_width)] {
+                for row in 0..self.game.rows() {
+                    let y = board_height - row as f64 * col_width - col_width;
+                    let s_matrix = t_matrix.trans(x, y).scale(col_width / 400.0, col_width / 400.0);
+
+                    image(&self.tile_bg, s_matrix, gl);
+
+                    if let Some(piece) = self.game.board[col][row] {
+                        draw_piece(&self.coin(piece.player), piece.kind, col_width, t_matrix.trans(x, y), gl);
+                    }
+
+                    image(&self.tile, s_matrix, gl);
+                }
+
+                rectangle([1.0, 1.0, 1.0, 0.5], [x, col_width, col_width, board_height - col_width], t_matrix, gl);
+            }
+        }
+
         // Animate'em
         self.animations.iter_mut().for_each(|animation| {
             animation.render(t_matrix, gl);
         }); 
 
-        rectangle(LIGHT_BLUE, [0.0, 0.0, board_size, col_width], t_matrix, gl);
+        rectangle(LIGHT_BLUE, [0.0, 0.0, board_width, col_width], t_matrix, gl);
         
-        for col in 0..COLUMNS {
+        for col in 0..self.game.columns() {
             let x = col as f64 * col_width;
  
-            for row in 0..ROWS {
-                let y = board_size - row as f64 * col_width - col_width;
+            for row in 0..self.game.rows() {
+                let y = board_height - row as f64 * col_width - col_width;
                 
-                if let Some(player) = &self.game.board[col as usize][row as usize] {
-                    match player {
-                        Player::Yellow => {
-                            image(&self.coin_yellow, t_matrix.trans(x, y).scale(col_width / 400.0, col_width / 400.0), gl);
-                        }
-
-                        Player::Red => {
-                            image(&self.coin_red, t_matrix.trans(x, y).scale(col_width / 400.0, col_width / 400.0), gl);
-                        }
-                    }
+                if self.hidden_columns.contains(&col) {
+                    // The column is being animated
+                } else if let Some(piece) = &self.game.board[col][row] {
+                    draw_piece(&self.coin(piece.player), piece.kind, col_width, t_matrix.trans(x, y), gl);
                 } 
                 
 
@@ -104,49 +336,89 @@ impl App {
               
 
             if let GameState::Running(player) = &self.game.state {
-                if let Some(col) = hover_column {
+                if !self.game.gravity {
+                    // A faded coin on the empty cell a click would place on
+                    if let Some((col, row)) = hover_cell.filter(|&(col, row)| self.game.board[col][row].is_none()) {
+                        let mut ghost = player.color();
+                        ghost[3] = 0.4;
+
+                        let y = board_height - row as f64 * col_width - col_width;
+                        ellipse(ghost, [col as f64 * col_width + col_width * 0.1, y + col_width * 0.1, col_width * 0.8, col_width * 0.8], t_matrix, gl);
+                    }
+                } else if let Some(col) = hover_column {
                     let s_matrix = t_matrix
                         .clone()
                         .trans(col as f64 * col_width, 0.0)
                         .scale(col_width / 400.0, col_width / 400.0);
                     
-                    match player {
-                        Player::Yellow => {
-                            image(&self.arrow_yellow, s_matrix, gl);
-                        }
-                        Player::Red => {
-                            image(&self.arrow_red, s_matrix, gl);
-                        }
-                    }
+                    image(&self.arrows[player], s_matrix, gl);
 
                 }
             } 
             
             // Status bar
             let bar_height = col_width / 1.7;
-            let bar_width = board_size;
+            let bar_width = board_width;
             let font_size = bar_height / 2.0;
 
-            rectangle(color::WHITE, [0.0, 0.0, board_size, bar_height], t_matrix, gl);
-            line_from_to(graphics::color::BLACK, 2.0, [0.0, bar_height], [board_size, bar_height], t_matrix, gl);
+            rectangle(color::WHITE, [0.0, 0.0, board_width, bar_height], t_matrix, gl);
+            line_from_to(graphics::color::BLACK, 2.0, [0.0, bar_height], [board_width, bar_height], t_matrix, gl);
 
             let text: String = match &self.game.state {
                 GameState::Starting => {
                     String::from("Forza quattro! Click anywhere")
                 }
                 GameState::Running(player) => {
-                    let p_text = player.text();
-                    format!("{p_text}s turn! Click to place")
+                    if let Some(offer) = self.game.draw_offer.filter(|offer| offer != player) {
+                        let o_text = self.name(offer);
+                        format!("{o_text} offers a draw! A to accept")
+                    } else if let Some(kind) = self.selected_power {
+                        let p_text = self.turn_text();
+                        format!("{p_text}'s turn! Click to drop the {}", kind.text())
+                    } else if self.game.holding.is_some() {
+                        let p_text = self.turn_text();
+                        format!("{p_text}'s turn! Click to put the coin back")
+                    } else if self.clock.is_some() {
+                        let p_text = self.turn_text();
+                        format!("{p_text}'s turn!")
+                    } else if self.game.rotations(*player) > 0 {
+                        let p_text = self.turn_text();
+                        format!("{p_text}'s turn! Click or turn with the arrows")
+                    } else {
+                        let p_text = self.turn_text();
+                        format!("{p_text}'s turn! Click to place")
+                    }
                 }
-                GameState::Win(player) => {
-                    let p_text = player.text();
+                GameState::Win { player, reason: WinReason::FourInARow } => {
+                    let p_text = self.name(*player);
                     format!("{p_text} wins! Click to reset")
                 }
-                GameState::Draw => {
+                GameState::Win { reason: WinReason::Resignation, .. } => {
+                    let o_text = self.game.eliminated.last().map_or(String::new(), |&resigned| self.name(resigned));
+                    format!("{o_text} resigns! Click to reset")
+                }
+                GameState::Win { player, reason: WinReason::Timeout } => {
+                    let p_text = self.name(*player);
+                    format!("{p_text} wins on time!")
+                }
+                GameState::Win { player, reason: WinReason::Captures } => {
+                    let p_text = self.name(*player);
+                    format!("{p_text} captured ten! Click to reset")
+                }
+                GameState::Draw(DrawReason::BoardFull) => {
                     String::from("It's a draw! Click to reset")
                 }
+                GameState::Draw(DrawReason::Agreement) => {
+                    String::from("Draw agreed! Click to reset")
+                }
+                GameState::Draw(DrawReason::Repetition) => {
+                    String::from("Draw by repetition! Click to reset")
+                }
             };
 
+            // Puzzles state their goal instead, the editor what it is setting up
+            let text = self.puzzle_text().or_else(|| self.editor_text()).unwrap_or(text);
+
             text::Text::new_color(color::BLACK, (bar_height * 0.5) as u32)
                 .draw(&text,
                       &mut self.font,
@@ -154,99 +426,376 @@ impl App {
                       t_matrix.trans(bar_width * 0.02, bar_height * 0.5 + font_size / 3.0),
                       gl).unwrap();
 
+            let mut info: Vec<String> = vec![];
+
+            if let Some(i) = self.puzzle {
+                info.push(format!("Puzzle {}/{}", i + 1, self.puzzles.len()));
+            }
+
+            if self.editor.is_some() {
+                info.push(String::from("Editor"));
+            }
+
+            match (&self.game_match, self.best_of) {
+                (Some(game_match), _) => info.push(game_match.text()),
+                (None, Some(best_of)) => info.push(format!("Bo{best_of}")),
+                (None, None) => (),
+            }
+
+            match (&self.game.state, &self.clock) {
+                (GameState::Starting, _) => {
+                    if self.variant != Variant::Classic {
+                        info.push(String::from(self.variant.text()));
+                    }
+
+                    if !self.game.gravity {
+                        info.push(String::from("No gravity"));
+                    }
+
+                    // Later rounds of a match alternate whatever the option says
+                    if self.game_match.is_none() {
+                        info.push(String::from(self.start_policy.text()));
+                    }
+
+                    info.extend(self.time_control.map(|tc| tc.text()));
+                }
+                (_, Some(clock)) => info.push(self.game.players.iter()
+                                                  .map(|&player| clock.text(player))
+                                                  .collect::<Vec<String>>()
+                                                  .join(" ")),
+                (_, None) => (),
+            };
+
+            let info_text = info.join("  ");
+            let info_width = self.font.width(font_size as u32, &info_text).unwrap_or(0.0);
+
+            // Turn order of a game of more than two seats, starting with the seat to move
+            if self.game.seats.len() > 2 {
+                let order = self.game.turn_order();
+                let coin_size = bar_height * 0.6;
+                let order_x = bar_width * 0.98 - info_width - font_size - order.len() as f64 * coin_size;
+
+                for (i, seat) in order.into_iter().enumerate() {
+                    image(&self.coins[&seat.player],
+                          t_matrix.trans(order_x + i as f64 * coin_size, bar_height * 0.2).scale(coin_size / 400.0, coin_size / 400.0),
+                          gl);
+                }
+            }
+
+            if !info.is_empty() {
+                text::Text::new_color(color::BLACK, font_size as u32)
+                    .draw(&info_text,
+                          &mut self.font,
+                          &c.draw_state,
+                          t_matrix.trans(bar_width * 0.98 - info_width, bar_height * 0.5 + font_size / 3.0),
+                          gl).unwrap();
+            }
+
             self.font.factory.encoder.flush(d);
         }
     }
 
+    /// Every legal move with the games of the database and the engine score, the hovered one marked
+    fn render_explorer(&mut self, c: Context, gl: &mut G2d, d: &mut Device) {
+        use graphics::*;
+
+        let Some(explorer) = &self.explorer else { return; };
+
+        let (x, width, row_height) = self.explorer_area();
+        let font_size = row_height / 1.6;
+        let hovered = self.get_explorer_row();
+
+        rectangle(color::WHITE, [x, 0.0, width, self.window_size.1], c.transform, gl);
+
+        let mut lines = vec![String::from("Explorer (F4 to close)"), String::new(), String::from(ExplorerRow::HEADER)];
+
+        if explorer.rows.is_empty() {
+            lines.push(String::from("No moves to explore"));
+        }
+
+        lines.extend(explorer.rows.iter().map(|row| row.text()));
+
+        for (i, line) in lines.iter().enumerate() {
+            if hovered.is_some_and(|row| row + 3 == i) {
+                rectangle(LIGHT_BLUE, [x, i as f64 * row_height, width, row_height], c.transform, gl);
+            }
+
+            let color = if hovered.is_some_and(|row| row + 3 == i) { color::WHITE } else { color::BLACK };
+
+            text::Text::new_color(color, font_size as u32)
+                .draw(line,
+                      &mut self.font,
+                      &c.draw_state,
+                      c.transform.trans(x + width * 0.04, (i as f64 + 0.7) * row_height),
+                      gl).unwrap();
+        }
+
+        self.font.factory.encoder.flush(d);
+    }
+
+    fn render_match_summary(&mut self, c: Context, gl: &mut G2d, d: &mut Device) {
+        use graphics::*;
+
+        let ((offset_x, offset_y), (board_size, _)) = self.get_dimensions();
+        let t_matrix = c.transform.trans(offset_x, offset_y);
+        let font_size = board_size / 20.0;
+
+        let Some(game_match) = &self.game_match else { return; };
+
+        let headline = match game_match.winner() {
+            Some(player) => format!("{} wins the match!", self.name(player)),
+            None => String::from("The match is drawn!"),
+        };
+
+        let mut lines = vec![headline];
+
+        lines.extend(game_match.players.iter()
+            .map(|&player| format!("{} {}", self.name(player), game_match.wins(player))));
+
+        lines.extend([
+            format!("Draws {}", game_match.draws()),
+            format!("Rounds {}/{}", game_match.rounds_played(), game_match.best_of),
+            String::from("Click to continue"),
+        ]);
+
+        for (i, line) in lines.iter().enumerate() {
+            text::Text::new_color(color::BLACK, font_size as u32)
+                .draw(line,
+                      &mut self.font,
+                      &c.draw_state,
+                      t_matrix.trans(board_size * 0.1, board_size * 0.3 + i as f64 * font_size * 2.0),
+                      gl).unwrap();
+        }
+
+        self.font.factory.encoder.flush(d);
+    }
+
+    fn render_statistics(&mut self, c: Context, gl: &mut G2d, d: &mut Device) {
+        use graphics::*;
+
+        let ((offset_x, offset_y), (board_size, _)) = self.get_dimensions();
+        let t_matrix = c.transform.trans(offset_x, offset_y);
+        let font_size = board_size / 32.0;
+
+        let names = self.profiles.names();
+        let mut lines = vec![String::from("Statistics (F1 to close)"), String::new()];
+
+        if names.is_empty() {
+            lines.push(String::from("No games played yet"));
+        }
+
+        for (i, name) in names.iter().enumerate() {
+            let stats = self.profiles.stats(name);
+            let marker = if i == self.selected_profile { ">" } else { " " };
+
+            lines.push(format!("{marker} {name:<16} {:>4} games  {}", stats.total.games(), stats.total.text()));
+        }
+
+        if let Some(name) = names.get(self.selected_profile) {
+            let stats = self.profiles.stats(name);
+
+            lines.push(String::new());
+            lines.push(name.clone());
+            lines.push(format!("Average length {:.1} moves", stats.average_length()));
+
+            if let Some((outcome, count)) = stats.streak {
+                lines.push(format!("Streak {}{}, best win streak {}", outcome.text(), count, stats.best_win_streak));
+            }
+
+            lines.push(format!("Puzzles solved {}/{}", self.profiles.solved(name), self.puzzles.len()));
+
+            for player in [Player::Yellow, Player::Red] {
+                let tally = stats.by_color.get(&player).copied().unwrap_or_default();
+                lines.push(format!("As {:<16} {}", player.text(), tally.text()));
+            }
+
+            for (opponent, tally) in &stats.by_opponent {
+                lines.push(format!("vs {opponent:<16} {}", tally.text()));
+            }
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            text::Text::new_color(color::BLACK, font_size as u32)
+                .draw(line,
+                      &mut self.font,
+                      &c.draw_state,
+                      t_matrix.trans(board_size * 0.05, font_size * 2.0 + i as f64 * font_size * 1.5),
+                      gl).unwrap();
+        }
+
+        self.font.factory.encoder.flush(d);
+    }
+
+    fn render_leaderboard(&mut self, c: Context, gl: &mut G2d, d: &mut Device) {
+        use graphics::*;
+
+        let ((offset_x, offset_y), (board_size, _)) = self.get_dimensions();
+        let t_matrix = c.transform.trans(offset_x, offset_y);
+        let font_size = board_size / 32.0;
+
+        let mut lines = vec![String::from("Leaderboard (F2 to close)"), String::new()];
+
+        for (i, (name, rating)) in rating::ladder(self.profiles.records()).iter().enumerate() {
+            lines.push(format!("{:>2}. {name:<20} {:>5.0} {:>5} games", i + 1, rating.elo, rating.games));
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            text::Text::new_color(color::BLACK, font_size as u32)
+                .draw(line,
+                      &mut self.font,
+                      &c.draw_state,
+                      t_matrix.trans(board_size * 0.05, font_size * 2.0 + i as f64 * font_size * 1.5),
+                      gl).unwrap();
+        }
+
+        self.font.factory.encoder.flush(d);
+    }
+
+    fn render_variant_menu(&mut self, c: Context, gl: &mut G2d, d: &mut Device) {
+        use graphics::*;
+
+        let ((offset_x, offset_y), (board_width, board_height)) = self.get_dimensions();
+        let t_matrix = c.transform.trans(offset_x, offset_y);
+        let font_size = board_height / 24.0;
+        let hovered = self.get_menu_entry();
+
+        text::Text::new_color(color::BLACK, font_size as u32)
+            .draw("Choose a variant (V to close)",
+                  &mut self.font,
+                  &c.draw_state,
+                  t_matrix.trans(board_width * 0.05, board_height * 0.25 - font_size * 2.0),
+                  gl).unwrap();
+
+        for (i, variant) in Variant::all().iter().enumerate() {
+            let marker = if hovered == Some(i) || self.variant == *variant { ">" } else { " " };
+            let line = format!("{marker} {}. {:<14} {}", i + 1, variant.text(), variant.description());
+
+            text::Text::new_color(color::BLACK, font_size as u32)
+                .draw(&line,
+                      &mut self.font,
+                      &c.draw_state,
+                      t_matrix.trans(board_width * 0.05, board_height * 0.25 + i as f64 * font_size * 2.0),
+                      gl).unwrap();
+        }
+
+        self.font.factory.encoder.flush(d);
+    }
+
+    fn select_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+        self.reset();
+        self.screen = Screen::Board;
+    }
+
     pub fn handle_click(&mut self) {
-        let (_, (board_size, _)) = self.get_dimensions();
-        let col_width = board_size / COLUMNS as f64;
+        let (_, (board_width, board_height)) = self.get_dimensions();
+        let col_width = board_width / self.game.columns() as f64;
 
         if !self.animations.is_empty() { return; };
         
-        
+        match self.screen {
+            Screen::Board => (),
+            Screen::MatchSummary => {
+                self.game_match = None;
+                self.screen = Screen::Board;
+                return;
+            }
+            Screen::Statistics | Screen::Leaderboard => return,
+            Screen::VariantMenu => {
+                if let Some(variant) = self.get_menu_entry().and_then(|i| Variant::all().get(i).copied()) {
+                    self.select_variant(variant);
+                }
+                return;
+            }
+        }
+
+        // A click on a move of the explorer plays it
+        if let Some(i) = self.get_explorer_row() {
+            if let (Some(explorer), GameState::Running(_), None) = (&self.explorer, &self.game.state, self.ai_to_move()) {
+                let mv = explorer.rows[i].mv;
+
+                self.selected_power = None;
+                self.play_move(mv);
+            }
+            return;
+        }
+
+        // The editor changes the clicked cell
+        if self.editor.is_some() {
+            let cell = self.get_mouse_cell();
+
+            if let (Some(editor), Some((col, row))) = (&mut self.editor, cell) {
+                editor.cycle(&mut self.game, col, row, false);
+            }
+            return;
+        }
+
+        // A decided puzzle moves on to the next one when solved, or starts over
+        if let Some(i) = self.puzzle {
+            if let Some(solved) = self.puzzles[i].solved(&self.game) {
+                let next = if solved { self.next_puzzle().unwrap_or(i) } else { i };
+                self.start_puzzle(next);
+                return;
+            }
+        }
 
         match self.game.state.clone() {
             GameState::Starting => {
-                self.game.state = self.game.state.next();
+                // The starting player option only picks who starts a single game or the first round of a match
+                match &self.game_match {
+                    Some(game_match) => self.first_player = game_match.starting_player(),
+                    None => {
+                        self.first_player = self.start_policy.first(self.last_game, &self.game.players);
+                        self.game_match = self.best_of.map(|best_of| Match::new(best_of, self.game.players.clone(), self.first_player));
+                    }
+                }
+                self.game.start(self.first_player);
+                self.clock = self.time_control.map(Clock::new);
             }
 
-            GameState::Running(player) => {
-                let coin_yellow = self.coin_yellow.clone();
-                let coin_red = self.coin_red.clone();
-                
-                if let Some(col) = self.get_mouse_column() {
-                    let x = col as f64 * col_width;
-                    let player_clone = player.clone();
-
-                    for (row, cell) in self.game.board[col].iter_mut().enumerate() {
-                        let y = board_size - row as f64 * col_width - col_width;
-                        
-                        
-
-                        if cell.is_none() {
-                            self.animations.push(
-                                Box::new(
-                                    Animation::new(
-                                        0.0,
-                                        GravityFloorState::new((x, 0.0), (0.0, 3.0), y),
-                                        move |state, t_matrix, gl| {
-                                            use graphics::*;
-                                            
-                                            match player_clone {
-                                                Player::Yellow => {
-                                                    image(&coin_yellow,
-                                                          t_matrix.trans(state.position.0, state.position.1).scale(col_width / 400.0, col_width / 400.0), gl);
-                                                }
-
-                                                Player::Red => {
-                                                    image(&coin_red, t_matrix.trans(state.position.0, state.position.1).scale(col_width / 400.0, col_width / 400.0), gl);
-                                                }
-                                            }
-                                        },
-                                        move |game: &mut Game| {
-                                            game.board[col][row] = Some(player_clone.clone());
-                                            game.update_state();
-                                        })));
-                            return;
-                        }
-                    }
-                } else {
+            GameState::Running(_) => {
+                if self.ai_to_move().is_some() {
                     return;
                 }
+
+                if !self.game.gravity {
+                    if let Some((col, row)) = self.get_mouse_cell() {
+                        self.play_move(Move::Place(col, row));
+                    }
+                } else if let Some(col) = self.get_mouse_column() {
+                    let mv = match self.selected_power {
+                        Some(kind) => Move::Power(kind, col),
+                        None => Move::Drop(col),
+                    };
+
+                    if self.game.legal_moves().contains(&mv) {
+                        self.selected_power = None;
+                        self.play_move(mv);
+                    }
+                }
             }
 
-            GameState::Win(_) | GameState::Draw => {
+            GameState::Win { .. } | GameState::Draw(_) => {
                 let cloned_board = self.game.board.clone();
 
-                for col_i in 0..COLUMNS {
+                for (col_i, column) in cloned_board.iter().enumerate() {
                     let x = col_i as f64 * col_width;
 
-                    for (row_i, &cell) in cloned_board[col_i as usize].iter().enumerate() {
-                        let y = board_size - row_i as f64 * col_width - col_width;
+                    for (row_i, &cell) in column.iter().enumerate() {
+                        let y = board_height - row_i as f64 * col_width - col_width;
  
                         match cell {
-                            Some(player) => {
-                                let coin_yellow = self.coin_yellow.clone();
-                                let coin_red = self.coin_red.clone();
+                            Some(piece) => {
+                                let coin = self.coin(piece.player);
                                 self.animations.push(
                                     Box::new(
                                         Animation::new(
-                                            row_i as f64 / (ROWS * 2) as f64,
-                                            GravityFloorState::new((x, y), (0.0, 0.0), board_size + col_width),
+                                            row_i as f64 / (self.game.rows() * 2) as f64,
+                                            GravityFloorState::new((x, y), (0.0, 0.0), board_height + col_width),
                                             move |state, t_matrix, gl| {
                                                 use graphics::*;
 
-                                                match player {
-                                                    Player::Yellow => {
-                                                        image(&coin_yellow,
-                                                              t_matrix.trans(state.position.0, state.position.1).scale(col_width / 400.0, col_width / 400.0), gl);
-                                                    }
-
-                                                    Player::Red => {
-                                                        image(&coin_red, t_matrix.trans(state.position.0, state.position.1).scale(col_width / 400.0, col_width / 400.0), gl);
-                                                    }
-                                                }
+                                                draw_piece(&coin, piece.kind, col_width, t_matrix.trans(state.position.0, state.position.1), gl);
                                             },
                                             move |_game: &mut Game| {
                                             })));
@@ -258,47 +807,823 @@ impl App {
                 }
 
                 self.reset();
+
+                if self.game_match.as_ref().is_some_and(|m| m.is_over()) {
+                    self.screen = Screen::MatchSummary;
+                }
             },
         }
     }
     
+    pub fn handle_right_click(&mut self) {
+        if !self.animations.is_empty() || self.screen != Screen::Board { return; };
+
+        if self.editor.is_some() {
+            let cell = self.get_mouse_cell();
+
+            if let (Some(editor), Some((col, row))) = (&mut self.editor, cell) {
+                editor.cycle(&mut self.game, col, row, true);
+            }
+            return;
+        }
+
+        if let GameState::Running(_) = self.game.state {
+            if self.ai_to_move().is_some() {
+                return;
+            }
+
+            if let Some(col) = self.get_mouse_column() {
+                self.pop_coin(col);
+            }
+        }
+    }
+
+    fn play_move(&mut self, mv: Move) {
+        match mv {
+            Move::Drop(col) => self.drop_coin(col, PieceKind::Normal),
+            Move::Pop(col) => self.pop_coin(col),
+            Move::Power(PieceKind::Bomb, col) => self.bomb_coin(col),
+            Move::Power(kind, col) => self.drop_coin(col, kind),
+            Move::Place(col, row) => self.place_coin(col, row),
+            Move::RotateLeft => self.rotate_board(false),
+            Move::RotateRight => self.rotate_board(true),
+        }
+    }
+
+    /// Turns the board, every coin falls from its turned cell to the new bottom
+    fn rotate_board(&mut self, clockwise: bool) {
+        let (_, (board_width, board_height)) = self.get_dimensions();
+        let col_width = board_width / self.game.columns() as f64;
+
+        let mv = if clockwise { Move::RotateRight } else { Move::RotateLeft };
+
+        if !self.game.legal_moves().contains(&mv) {
+            return;
+        }
+
+        // One of the coins plays the move when it lands, the board stays hidden until all have landed
+        let mut apply = Some(mv);
+
+        for (col, column) in rotation::rotated(&self.game.board, clockwise).into_iter().enumerate() {
+            let x = col as f64 * col_width;
+            let coins = column.into_iter()
+                .enumerate()
+                .filter_map(|(row, cell)| cell.map(|piece| (row, piece)));
+
+            // The coins keep their order, the n-th coin from the bottom lands in row n
+            for (landing_row, (row, piece)) in coins.enumerate() {
+                let coin = self.coin(piece.player);
+                let y = board_height - row as f64 * col_width - col_width;
+                let floor = board_height - landing_row as f64 * col_width - col_width;
+                let finish = apply.take();
+
+                self.animations.push(
+                    Box::new(
+                        Animation::new(
+                            0.0,
+                            GravityFloorState::new((x, y), (0.0, 0.0), floor),
+                            move |state, t_matrix, gl| {
+                                use graphics::*;
+
+                                draw_piece(&coin, piece.kind, col_width, t_matrix.trans(state.position.0, state.position.1), gl);
+                            },
+                            move |game: &mut Game| {
+                                if let Some(mv) = finish {
+                                    game.apply(mv);
+                                }
+                            })));
+            }
+        }
+
+        self.hidden_columns = (0..self.game.columns()).collect();
+    }
+
+    /// Fades and scales the coin in on its cell, there is nothing to fall without gravity
+    fn place_coin(&mut self, col: usize, row: usize) {
+        let (_, (board_width, board_height)) = self.get_dimensions();
+        let col_width = board_width / self.game.columns() as f64;
+
+        let GameState::Running(player) = self.game.state else { return; };
+
+        if !self.game.legal_moves().contains(&Move::Place(col, row)) {
+            return;
+        }
+
+        let coin = self.coin(player);
+        let x = col as f64 * col_width;
+        let y = board_height - row as f64 * col_width - col_width;
+
+        self.animations.push(
+            Box::new(
+                Animation::new(
+                    0.0,
+                    ScaleInState::default(),
+                    move |state, t_matrix, gl| {
+                        use graphics::*;
+
+                        let size = col_width * state.progress;
+                        let margin = (col_width - size) / 2.0;
+
+                        Image::new_color([1.0, 1.0, 1.0, state.progress as f32])
+                            .draw(&coin, &DrawState::default(), t_matrix.trans(x + margin, y + margin).scale(size / 400.0, size / 400.0), gl);
+                    },
+                    move |game: &mut Game| {
+                        game.apply(Move::Place(col, row));
+                    })));
+    }
+
+    fn pop_coin(&mut self, col: usize) {
+        let (_, (board_width, board_height)) = self.get_dimensions();
+        let col_width = board_width / self.game.columns() as f64;
+
+        let GameState::Running(player) = self.game.state else { return; };
+
+        if !self.game.legal_moves().contains(&Move::Pop(col)) {
+            return;
+        }
+
+        let x = col as f64 * col_width;
+        let coin = self.coin(player);
+
+        self.animations.push(
+            Box::new(
+                Animation::new(
+                    0.0,
+                    PopState::new((x, board_height - col_width), (0.0, -300.0), -col_width),
+                    move |state, t_matrix, gl| {
+                        use graphics::*;
+
+                        image(&coin, t_matrix.trans(state.position.0, state.position.1).scale(col_width / 400.0, col_width / 400.0), gl);
+                    },
+                    move |game: &mut Game| {
+                        game.apply(Move::Pop(col));
+                    })));
+
+        // The coins above fall down by one row
+        for (row, cell) in self.game.board[col].iter().enumerate().skip(1) {
+            let Some(piece) = cell else { break; };
+            let coin = self.coin(piece.player);
+            let y = board_height - row as f64 * col_width - col_width;
+
+            self.animations.push(
+                Box::new(
+                    Animation::new(
+                        0.0,
+                        GravityFloorState::new((x, y), (0.0, 0.0), y + col_width),
+                        move |state, t_matrix, gl| {
+                            use graphics::*;
+
+                            image(&coin, t_matrix.trans(state.position.0, state.position.1).scale(col_width / 400.0, col_width / 400.0), gl);
+                        },
+                        move |_game: &mut Game| {
+                        })));
+        }
+
+        self.hidden_columns = vec![col];
+    }
+
+    fn coin(&self, player: Player) -> G2dTexture {
+        self.coins[&player].clone()
+    }
+
+    fn drop_coin(&mut self, col: usize, kind: PieceKind) {
+        let (_, (board_width, board_height)) = self.get_dimensions();
+        let col_width = board_width / self.game.columns() as f64;
+
+        let GameState::Running(player) = self.game.state else { return; };
+        let Some(row) = self.game.free_row(col) else { return; };
+
+        let mv = match kind {
+            PieceKind::Normal => Move::Drop(col),
+            kind => Move::Power(kind, col),
+        };
+
+        // An anvil falls through to the bottom, crushing the coins it passes
+        let (row, crushed) = match kind {
+            PieceKind::Anvil => {
+                let crushed: Vec<(f64, PieceKind, G2dTexture)> = self.game.board[col].iter()
+                    .enumerate()
+                    .filter_map(|(row, cell)| cell.map(|piece| (row, piece)))
+                    .map(|(row, piece)| (board_height - row as f64 * col_width - col_width, piece.kind, self.coin(piece.player)))
+                    .collect();
+
+                self.hidden_columns = vec![col];
+                (0, crushed)
+            }
+            _ => (row, vec![]),
+        };
+
+        let coin = self.coin(player);
+        let x = col as f64 * col_width;
+        let y = board_height - row as f64 * col_width - col_width;
+
+        self.animations.push(
+            Box::new(
+                Animation::new(
+                    0.0,
+                    GravityFloorState::new((x, 0.0), (0.0, 3.0), y),
+                    move |state, t_matrix, gl| {
+                        use graphics::*;
+
+                        for (crushed_y, crushed_kind, crushed_coin) in &crushed {
+                            if *crushed_y >= state.position.1 + col_width {
+                                draw_piece(crushed_coin, *crushed_kind, col_width, t_matrix.trans(x, *crushed_y), gl);
+                            }
+                        }
+
+                        draw_piece(&coin, kind, col_width, t_matrix.trans(state.position.0, state.position.1), gl);
+                    },
+                    move |game: &mut Game| {
+                        game.apply(mv);
+                    })));
+    }
+
+    /// Drops the bomb onto the top coin of the column, both go up in the blast
+    fn bomb_coin(&mut self, col: usize) {
+        let (_, (board_width, board_height)) = self.get_dimensions();
+        let col_width = board_width / self.game.columns() as f64;
+
+        let GameState::Running(player) = self.game.state else { return; };
+
+        if !self.game.legal_moves().contains(&Move::Power(PieceKind::Bomb, col)) {
+            return;
+        }
+
+        let Some(target_row) = self.game.board[col].iter().rposition(|cell| cell.is_some()) else { return; };
+
+        let coin = self.coin(player);
+        let x = col as f64 * col_width;
+        let target_y = board_height - target_row as f64 * col_width - col_width;
+
+        self.animations.push(
+            Box::new(
+                Animation::new(
+                    0.0,
+                    BombState::new(GravityFloorState::new((x, 0.0), (0.0, 3.0), target_y - col_width)),
+                    move |state, t_matrix, gl| {
+                        use graphics::*;
+
+                        match state.blast {
+                            None => {
+                                let (bomb_x, bomb_y) = state.fall.position;
+                                draw_piece(&coin, PieceKind::Bomb, col_width, t_matrix.trans(bomb_x, bomb_y), gl);
+                            }
+                            Some(blast) => {
+                                let radius = col_width * (0.5 + blast);
+
+                                ellipse([1.0, 0.6, 0.0, 1.0 - blast as f32],
+                                        [x + col_width / 2.0 - radius, target_y - radius, radius * 2.0, radius * 2.0],
+                                        t_matrix, gl);
+                            }
+                        }
+                    },
+                    move |game: &mut Game| {
+                        game.apply(Move::Power(PieceKind::Bomb, col));
+                    })));
+    }
+
+    pub fn handle_key(&mut self, key: Key) {
+        if !self.animations.is_empty() { return; };
+
+        match (self.screen, key) {
+            (Screen::Board, Key::F1) => {
+                self.screen = Screen::Statistics;
+                return;
+            }
+            (Screen::Board, Key::F2) => {
+                self.screen = Screen::Leaderboard;
+                return;
+            }
+            (Screen::Board, Key::F3) => {
+                self.toggle_puzzles();
+                return;
+            }
+            (Screen::Board, Key::F4) => {
+                // The engine scores would give puzzles away
+                self.explorer = match (&self.explorer, self.puzzle) {
+                    (None, None) => Some(Explorer::new(&self.game, &self.database)),
+                    _ => None,
+                };
+                return;
+            }
+            (Screen::Board, Key::F5) => {
+                self.threats = !self.threats;
+                return;
+            }
+            (Screen::Board, Key::F6) => {
+                self.toggle_editor();
+                return;
+            }
+            (Screen::Board, Key::Backspace) => {
+                self.take_back();
+                return;
+            }
+            (Screen::Statistics, Key::F1) | (Screen::Leaderboard, Key::F2) | (Screen::VariantMenu, Key::V) => {
+                self.screen = Screen::Board;
+                return;
+            }
+            (Screen::VariantMenu, _) => {
+                let digits = [Key::D1, Key::D2, Key::D3, Key::D4, Key::D5, Key::D6, Key::D7, Key::D8, Key::D9];
+
+                if let Some(variant) = digits.iter().position(|&digit| digit == key).and_then(|i| Variant::all().get(i).copied()) {
+                    self.select_variant(variant);
+                }
+                return;
+            }
+            (Screen::Statistics, Key::Up) => {
+                self.selected_profile = self.selected_profile.saturating_sub(1);
+                return;
+            }
+            (Screen::Statistics, Key::Down) => {
+                self.selected_profile = (self.selected_profile + 1).min(self.profiles.names().len().saturating_sub(1));
+                return;
+            }
+            (Screen::Board, _) => (),
+            _ => return
+        }
+
+        // Who plays which color and the time control can still be chosen in the editor
+        if self.editor.is_some() && !matches!(key, Key::S | Key::T) {
+            self.handle_editor_key(key);
+            return;
+        }
+
+        if let (GameState::Starting, Key::T) = (&self.game.state, key) {
+            let next = match self.time_control {
+                None => 0,
+                Some(tc) => TIME_CONTROLS.iter().position(|&preset| preset == tc).map_or(0, |i| i + 1),
+            };
+
+            self.time_control = TIME_CONTROLS.get(next).copied();
+        }
+
+        if let (GameState::Starting, Key::V) = (&self.game.state, key) {
+            self.screen = Screen::VariantMenu;
+        }
+
+        if let (GameState::Starting, Key::G) = (&self.game.state, key) {
+            if self.variant.rules().gravity_optional() {
+                self.gravity = !self.gravity;
+                self.reset();
+            }
+        }
+
+        // The match length, colors and starting player option can only change between matches
+        if let (GameState::Starting, None) = (&self.game.state, &self.game_match) {
+            match key {
+                Key::P => self.start_policy = self.start_policy.next(),
+                Key::M => {
+                    let next = match self.best_of {
+                        None => 0,
+                        Some(best_of) => MATCH_LENGTHS.iter().position(|&length| length == best_of).map_or(0, |i| i + 1),
+                    };
+
+                    self.best_of = MATCH_LENGTHS.get(next).copied();
+                }
+                Key::S => {
+                    // Everyone moves on by one seat, with two players that swaps the colors
+                    let seats = self.seats();
+                    let mut seated: Vec<Participant> = seats.iter()
+                        .filter_map(|seat| self.participants.remove(seat))
+                        .collect();
+
+                    seated.rotate_right(1);
+                    self.participants.extend(seats.into_iter().zip(seated));
+                }
+                _ => ()
+            }
+        }
+
+        // Number keys pick a power coin, picking it again goes back to a normal coin
+        if let GameState::Running(player) = self.game.state {
+            let digits = [Key::D1, Key::D2, Key::D3, Key::D4];
+            let power = digits.iter().position(|&digit| digit == key).and_then(|i| PieceKind::POWERS.get(i));
+
+            if let (Some(&kind), None) = (power, self.ai_to_move()) {
+                if self.selected_power == Some(kind) {
+                    self.selected_power = None;
+                } else if self.game.powers(player).contains(&kind) {
+                    self.selected_power = Some(kind);
+                }
+            }
+        }
+
+        // The arrow keys turn the board in Rotation
+        if let (GameState::Running(_), None) = (&self.game.state, self.ai_to_move()) {
+            let mv = match key {
+                Key::Left => Some(Move::RotateLeft),
+                Key::Right => Some(Move::RotateRight),
+                _ => None,
+            };
+
+            if let Some(mv) = mv.filter(|mv| self.game.legal_moves().contains(mv)) {
+                self.play_move(mv);
+                return;
+            }
+        }
+
+        if let GameState::Running(_) = self.game.state {
+            // Shortcuts always act for a human, the next one in turn order while an AI is to move
+            let human = self.game.turn_order().into_iter()
+                .find(|&seat| self.participant(seat).difficulty().is_none());
+
+            let Some(Seat { player, .. }) = human else { return; };
+
+            match key {
+                Key::R => self.game.resign(player),
+                Key::D => self.game.offer_draw(player),
+                Key::A => self.game.accept_draw(player),
+                _ => ()
+            }
+        }
+    }
+
     pub fn update(&mut self, args: &UpdateArgs) {
+        // The clock pauses while a coin is falling
+        if self.animations.is_empty() {
+            if let (GameState::Running(player), Some(clock)) = (self.game.state.clone(), &mut self.clock) {
+                if clock.tick(player, args.dt) {
+                    self.game.time_out(player);
+                }
+            }
+        }
+
+        if let (Screen::Board, true, GameState::Running(_)) = (self.screen, self.animations.is_empty(), self.game.state.clone()) {
+            if let Some(difficulty) = self.ai_to_move() {
+                if let Some(mv) = ai::best_move(&self.game, difficulty) {
+                    self.play_move(mv);
+                }
+            }
+        }
+
         self.animations.iter_mut().for_each(|animation| {
             animation.update(&mut self.game, args.dt);
         });
 
         self.animations.retain(|animation| animation.is_running());    
+
+        if self.animations.is_empty() {
+            self.hidden_columns.clear();
+        }
+
+        // The explorer follows every move and every move taken back
+        if self.explorer.as_ref().is_some_and(|explorer| explorer.key != self.game.key()) {
+            self.explorer = Some(Explorer::new(&self.game, &self.database));
+        }
+
+        if !self.result_recorded && matches!(self.game.state, GameState::Win { .. } | GameState::Draw(_)) {
+            self.finish_game();
+        }
     }
 
-    pub fn reset(&mut self) {
-        self.game = Game {
-            board: vec![vec![None; 6]; 7],
-            state: GameState::Starting,
+    fn finish_game(&mut self) {
+        // Puzzles count for the puzzle statistics only
+        if let Some(i) = self.puzzle {
+            self.result_recorded = true;
+
+            if self.puzzles[i].solved(&self.game) == Some(true) {
+                let position = self.puzzles[i].position.clone();
+
+                if let Err(e) = self.profiles.solve(&self.solver(), &position) {
+                    eprintln!("Failed to save solved puzzle: {}", e);
+                }
+            }
+            return;
+        }
+
+        if let Some(game_match) = &mut self.game_match {
+            game_match.record(&self.game.state);
+        }
+
+        let winner = match self.game.state {
+            GameState::Win { player, .. } => Some(player),
+            _ => None
+        };
+
+        self.last_game = Some((self.first_player, winner));
+        self.result_recorded = true;
+
+        // The profiles and the leaderboard only count games of two
+        if self.game.seats.len() != 2 {
+            return;
+        }
+
+        let record = GameRecord {
+            yellow: self.name(Player::Yellow),
+            red: self.name(Player::Red),
+            first: self.first_player,
+            winner,
+            moves: self.game.moves.clone(),
+            variant: self.game.variant,
         };
+
+        if let Err(e) = self.profiles.add(record) {
+            eprintln!("Failed to save game record: {}", e);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.game = Game::initial(self.variant, self.seats()).with_gravity(self.gravity);
+        self.selected_power = None;
+        self.result_recorded = false;
+    }
+
+    /// Takes back the last move while the explorer is open, and the AI's moves
+    /// before it, so a human is to move. Matches, puzzles and timed games can't
+    /// be taken back, and a game with a move taken back isn't recorded.
+    fn take_back(&mut self) {
+        if self.explorer.is_none() || self.game_match.is_some() || self.puzzle.is_some() || self.clock.is_some() {
+            return;
+        }
+
+        if !self.game.undo() {
+            return;
+        }
+
+        while self.ai_to_move().is_some() && self.game.undo() {}
+
+        self.selected_power = None;
+        self.result_recorded = true;
+    }
+
+    /// Opens the editor on an empty board before a game, or leaves it
+    fn toggle_editor(&mut self) {
+        if self.editor.take().is_some() {
+            self.reset();
+            return;
+        }
+
+        // Only a board with nothing else to the position can be set up by hand
+        let editable = self.game.players.len() == 2 && self.game.rules().board_is_position();
+
+        if !editable || self.puzzle.is_some() || self.game_match.is_some() || !matches!(self.game.state, GameState::Starting) {
+            return;
+        }
+
+        self.reset();
+        self.editor = Some(Editor::new(&self.game));
+    }
+
+    fn handle_editor_key(&mut self, key: Key) {
+        let Some(editor) = &mut self.editor else { return; };
+
+        match key {
+            Key::Tab => editor.switch_sides(&self.game),
+            Key::C => editor.clear(&mut self.game),
+            Key::E | Key::I => {
+                if self.clipboard.is_none() {
+                    self.clipboard = Clipboard::new().map_err(|e| eprintln!("Failed to open clipboard: {}", e)).ok();
+                }
+
+                if key == Key::E {
+                    editor.export(&self.game, self.clipboard.as_mut());
+                } else {
+                    editor.import(&mut self.game, self.clipboard.as_mut());
+                }
+            }
+            Key::Return => {
+                let Ok(first_player) = editor.play(&mut self.game) else { return; };
+
+                // The start policy picks who starts the next game from who started this one
+                self.first_player = first_player;
+                self.editor = None;
+                self.clock = self.time_control.map(Clock::new);
+
+                // The record of the game couldn't be replayed from the start
+                self.result_recorded = true;
+            }
+            _ => ()
+        }
+    }
+
+    /// The player to move, or why the position can't be played, in the editor
+    fn editor_text(&self) -> Option<String> {
+        let editor = self.editor.as_ref()?;
+
+        Some(editor.message.clone()
+            .or_else(|| editor.problem(&self.game))
+            .unwrap_or(format!("{} to move! Enter to play", self.name(editor.to_move))))
+    }
+
+    /// Everyone at the table in turn order
+    fn seats(&self) -> Vec<Seat> {
+        self.participants.keys().copied().collect()
+    }
+
+    fn participant(&self, seat: Seat) -> &Participant {
+        &self.participants[&seat]
+    }
+
+    /// The AI's difficulty when an AI is to move
+    fn ai_to_move(&self) -> Option<Difficulty> {
+        // The engine defends puzzles until they are decided
+        if let Some(i) = self.puzzle {
+            let puzzle = &self.puzzles[i];
+            let defending = matches!(self.game.state, GameState::Running(player) if player != puzzle.player);
+
+            return (defending && puzzle.solved(&self.game).is_none()).then_some(Difficulty::Hard);
+        }
+
+        self.game.seat().and_then(|seat| self.participant(seat).difficulty())
+    }
+
+    /// Starts the first puzzle the solver hasn't solved yet, or leaves puzzle mode
+    fn toggle_puzzles(&mut self) {
+        if self.puzzle.take().is_some() {
+            self.reset();
+            return;
+        }
+
+        if self.puzzles.is_empty() || self.game_match.is_some() || !matches!(self.game.state, GameState::Starting) {
+            return;
+        }
+
+        let solver = self.solver();
+        let first = self.puzzles.iter()
+            .position(|puzzle| !self.profiles.is_solved(&solver, &puzzle.position))
+            .unwrap_or(0);
+
+        self.start_puzzle(first);
+    }
+
+    fn start_puzzle(&mut self, i: usize) {
+        match self.puzzles[i].game() {
+            Ok(game) => {
+                self.game = game;
+                self.puzzle = Some(i);
+                self.explorer = None;
+                self.clock = None;
+                self.selected_power = None;
+                self.result_recorded = false;
+            }
+            Err(e) => eprintln!("Failed to start puzzle {}: {}", i + 1, e),
+        }
+    }
+
+    /// The next puzzle after the current one the solver hasn't solved yet
+    fn next_puzzle(&self) -> Option<usize> {
+        let current = self.puzzle?;
+        let solver = self.solver();
+
+        (1..self.puzzles.len())
+            .map(|offset| (current + offset) % self.puzzles.len())
+            .find(|&i| !self.profiles.is_solved(&solver, &self.puzzles[i].position))
+    }
+
+    /// Puzzles are solved for the first human at the table
+    fn solver(&self) -> String {
+        self.participants.values()
+            .find(|participant| participant.difficulty().is_none())
+            .map_or(String::from(Player::Yellow.text()), |participant| participant.name.clone())
+    }
+
+    /// The goal of the puzzle and how it went, in puzzle mode
+    fn puzzle_text(&self) -> Option<String> {
+        let puzzle = &self.puzzles[self.puzzle?];
+
+        Some(match puzzle.solved(&self.game) {
+            Some(true) => String::from("Solved! Click for the next puzzle"),
+            Some(false) => String::from("Not solved! Click to try again"),
+            None => {
+                let left = puzzle.moves - (self.game.moves.len() as u32).div_ceil(2);
+                format!("{}! {left} moves left", puzzle.goal())
+            }
+        })
+    }
+
+    /// Who is to move, with their team in team play
+    fn turn_text(&self) -> String {
+        let Some(seat) = self.game.seat() else { return String::new(); };
+        let name = &self.participant(seat).name;
+
+        if self.game.seats.len() > self.game.players.len() {
+            format!("{} team, {name}", seat.player.text())
+        } else {
+            name.clone()
+        }
+    }
+
+    /// Everyone playing the color, teammates joined with `&`
+    fn name(&self, player: Player) -> String {
+        self.participants.iter()
+            .filter(|(seat, _)| seat.player == player)
+            .map(|(_, participant)| participant.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(" & ")
     }
 
     pub fn set_mouse_pos(&mut self, pos: Pos) {
         self.mouse_pos = pos;
     }
 
+    /// The board is one row higher than the game's board, the top row holds the status bar
     fn get_dimensions(&self) -> (Pos, Size) {
         let (w, h) = self.window_size;
-        let board_size: f64 = w.min(h);
-        let offset_x = (w - board_size) / 2.0;
-        let offset_y = (h - board_size) / 2.0;
 
-        ((offset_x, offset_y), (board_size, board_size))
+        // The opening explorer takes the right of the window
+        let w = if self.explorer.is_some() { w * (1.0 - EXPLORER_WIDTH) } else { w };
+        let columns = self.game.columns() as f64;
+        let rows = self.game.rows() as f64 + 1.0;
+
+        // Leave room for the ghost columns of a wrapping board or the piles beside the board
+        let rules = self.game.rules();
+        let side_panels = rules.captures_to_win().is_some() || !rules.powers().is_empty() || rules.rotations() > 0;
+        let margin_columns = if rules.wraps() || side_panels { 2.0 } else { 0.0 };
+
+        let col_width = (w / (columns + margin_columns)).min(h / rows);
+        let (board_width, board_height) = (col_width * columns, col_width * rows);
+        let offset_x = (w - board_width) / 2.0;
+        let offset_y = (h - board_height) / 2.0;
+
+        ((offset_x, offset_y), (board_width, board_height))
+    }
+
+
+    /// The area beside the board for a player's pile or power coins as (center x, top, height).
+    /// The players are seated alternately left and right of the board.
+    fn side_panel(&self, player: Player) -> (f64, f64, f64) {
+        let ((offset_x, _), (board_width, board_height)) = self.get_dimensions();
+        let col_width = board_width / self.game.columns() as f64;
+
+        let players = &self.game.players;
+        let i = players.iter().position(|&p| p == player).unwrap_or(0);
+        let on_side = (players.len() + 1 - i % 2) / 2;
+        let height = (board_height - col_width) / on_side as f64;
+        let x = if i % 2 == 0 { -offset_x / 2.0 } else { board_width + offset_x / 2.0 };
+
+        (x, col_width + (i / 2) as f64 * height, height)
     }
 
+    /// The opening explorer's area on the right of the window as (x, width, row height)
+    fn explorer_area(&self) -> (f64, f64, f64) {
+        let (w, h) = self.window_size;
+        let width = w * EXPLORER_WIDTH;
+
+        (w - width, width, (width / 22.0).min(h / 30.0) * 1.6)
+    }
+
+    /// The explorer row under the mouse, laid out like in `render_explorer`
+    fn get_explorer_row(&self) -> Option<usize> {
+        let explorer = self.explorer.as_ref()?;
+        let (x, _, row_height) = self.explorer_area();
+
+        if self.mouse_pos.0 < x {
+            return None;
+        }
+
+        // The title and the header come before the moves
+        let row = (self.mouse_pos.1 / row_height) as usize;
+
+        row.checked_sub(3).filter(|&i| i < explorer.rows.len())
+    }
+
+    /// The variant menu entry under the mouse, laid out like in `render_variant_menu`
+    fn get_menu_entry(&self) -> Option<usize> {
+        let ((_, oy), (_, board_height)) = self.get_dimensions();
+        let font_size = board_height / 24.0;
+        let y = self.mouse_pos.1 - oy - board_height * 0.25 + font_size;
+
+        if y < 0.0 {
+            return None;
+        }
+
+        Some((y / (font_size * 2.0)) as usize).filter(|&i| i < Variant::all().len())
+    }
+
+    /// The cell under the mouse as (column, row), rows counted bottom up like the game's board
+    fn get_mouse_cell(&self) -> Option<(usize, usize)> {
+        let ((_, oy), (w, h)) = self.get_dimensions();
+        let col = self.get_mouse_column()?;
+
+        let column_width = w / self.game.columns() as f64;
+        let row_index = (h - (self.mouse_pos.1 - oy)) / column_width;
+
+        if row_index < 0.0 || row_index >= self.game.rows() as f64 {
+            return None;
+        }
+
+        Some((col, row_index as usize))
+    }
 
     fn get_mouse_column(&self) -> Option<usize> {
         let ((ox, oy), (w, _h)) = self.get_dimensions();
         let pos = (self.mouse_pos.0 - ox, self.mouse_pos.1 - oy);
 
-        let column_width = w / COLUMNS as f64;
+        let columns = self.game.columns() as f64;
+        let column_width = w / columns;
         let column_index = pos.0 / column_width;
 
-        if column_index < 0.0 || column_index >= COLUMNS as f64 {
+        // The ghost columns stand for the opposite edge
+        if self.game.rules().wraps() {
+            if (-1.0..0.0).contains(&column_index) {
+                return Some(self.game.columns() - 1);
+            }
+            if (columns..columns + 1.0).contains(&column_index) {
+                return Some(0);
+            }
+        }
+
+        if column_index < 0.0 || column_index >= columns {
             return None;
         }
 
@@ -306,3 +1631,33 @@ impl App {
     }
 }
 
+/// Draws a coin with the mark of its power, if it has one
+fn draw_piece(coin: &G2dTexture, kind: PieceKind, size: f64, transform: Matrix2d, gl: &mut G2d) {
+    use graphics::*;
+
+    let mark = [0.15, 0.15, 0.15, 1.0];
+
+    image(coin, transform.scale(size / 400.0, size / 400.0), gl);
+
+    match kind {
+        PieceKind::Normal => (),
+        PieceKind::Anvil => {
+            rectangle(mark, [size * 0.25, size * 0.35, size * 0.5, size * 0.12], transform, gl);
+            rectangle(mark, [size * 0.42, size * 0.47, size * 0.16, size * 0.12], transform, gl);
+            rectangle(mark, [size * 0.32, size * 0.59, size * 0.36, size * 0.08], transform, gl);
+        }
+        PieceKind::Wall => {
+            for i in 0..3 {
+                rectangle(mark, [size * 0.28, size * (0.33 + i as f64 * 0.13), size * 0.44, size * 0.08], transform, gl);
+            }
+        }
+        PieceKind::Bomb => {
+            ellipse(mark, [size * 0.32, size * 0.36, size * 0.36, size * 0.36], transform, gl);
+            line_from_to(mark, size * 0.02, [size * 0.6, size * 0.4], [size * 0.68, size * 0.28], transform, gl);
+        }
+        PieceKind::Double => {
+            ellipse(mark, [size * 0.27, size * 0.4, size * 0.2, size * 0.2], transform, gl);
+            ellipse(mark, [size * 0.53, size * 0.4, size * 0.2, size * 0.2], transform, gl);
+        }
+    }
+}
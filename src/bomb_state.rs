@@ -0,0 +1,38 @@
+use crate::animation::AnimationStatus;
+use crate::animation::animation_state::AnimationState;
+use crate::gravity_floor_state::GravityFloorState;
+
+const BLAST_TIME: f64 = 0.4;
+
+/// Drops a bomb onto its target, then grows the blast from 0 to 1
+#[derive(Debug)]
+pub struct BombState {
+    pub fall: GravityFloorState,
+    pub blast: Option<f64>,
+}
+
+impl BombState {
+    pub fn new(fall: GravityFloorState) -> Self {
+        Self { fall, blast: None }
+    }
+}
+
+impl AnimationState for BombState {
+    fn update(&mut self, dt: f64) -> AnimationStatus {
+        let Some(blast) = &mut self.blast else {
+            if let AnimationStatus::Finished = self.fall.update(dt) {
+                self.blast = Some(0.0);
+            }
+
+            return AnimationStatus::Running;
+        };
+
+        *blast += dt / BLAST_TIME;
+
+        if *blast >= 1.0 {
+            return AnimationStatus::Finished;
+        }
+
+        AnimationStatus::Running
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::player::Player;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum TimeControl {
+    SuddenDeath { base: f64 },
+    /// Adds `increment` seconds after every move
+    Fischer { base: f64, increment: f64 },
+    /// Gives back the time spent on a move, but at most `delay` seconds
+    Bronstein { base: f64, delay: f64 },
+}
+
+impl TimeControl {
+    pub fn base(&self) -> f64 {
+        match self {
+            TimeControl::SuddenDeath { base }
+            | TimeControl::Fischer { base, .. }
+            | TimeControl::Bronstein { base, .. } => *base
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let minutes = self.base() / 60.0;
+
+        match self {
+            TimeControl::SuddenDeath { .. } => format!("{minutes}+0"),
+            TimeControl::Fischer { increment, .. } => format!("{minutes}+{increment}"),
+            TimeControl::Bronstein { delay, .. } => format!("{minutes} d{delay}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: HashMap<Player, f64>,
+    active: Option<Player>,
+    turn_time: f64,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Self {
+            control,
+            remaining: HashMap::new(),
+            active: None,
+            turn_time: 0.0,
+        }
+    }
+
+    /// Runs the clock of `player`, pressing the previous player's clock first
+    /// if the turn has changed. Returns true once `player` is out of time.
+    pub fn tick(&mut self, player: Player, dt: f64) -> bool {
+        if self.active != Some(player) {
+            if let Some(previous) = self.active {
+                self.press(previous);
+            }
+
+            self.active = Some(player);
+            self.turn_time = 0.0;
+        }
+
+        self.turn_time += dt;
+
+        let base = self.control.base();
+        let remaining = self.remaining.entry(player).or_insert(base);
+        *remaining = (*remaining - dt).max(0.0);
+
+        *remaining <= 0.0
+    }
+
+    pub fn remaining(&self, player: Player) -> f64 {
+        *self.remaining.get(&player).unwrap_or(&self.control.base())
+    }
+
+    pub fn text(&self, player: Player) -> String {
+        let remaining = self.remaining(player);
+
+        if remaining < 10.0 {
+            format!("{:.1}", remaining)
+        } else {
+            let seconds = remaining.ceil() as u64;
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        }
+    }
+
+    fn press(&mut self, player: Player) {
+        let bonus = match self.control {
+            TimeControl::SuddenDeath { .. } => 0.0,
+            TimeControl::Fischer { increment, .. } => increment,
+            TimeControl::Bronstein { delay, .. } => self.turn_time.min(delay),
+        };
+
+        let base = self.control.base();
+        *self.remaining.entry(player).or_insert(base) += bonus;
+    }
+}
@@ -1,11 +1,25 @@
 use opengl_graphics::OpenGL;
 use piston::EventSettings;
 
+use crate::clock::TimeControl;
+
 pub const RENDERER: OpenGL = OpenGL::V4_5;
 pub const COLUMNS: i32 = 7;
 pub const ROWS: i32 = 6;
 pub const GRAVITY: f64 = 981.0;
 
+pub const MATCH_LENGTHS: [u32; 3] = [3, 5, 7];
+
+/// The share of the window the opening explorer takes beside the board
+pub const EXPLORER_WIDTH: f64 = 0.32;
+
+pub const TIME_CONTROLS: [TimeControl; 4] = [
+    TimeControl::SuddenDeath { base: 60.0 },
+    TimeControl::SuddenDeath { base: 180.0 },
+    TimeControl::Fischer { base: 180.0, increment: 2.0 },
+    TimeControl::Bronstein { base: 300.0, delay: 3.0 },
+];
+
 pub const EVENT_SETTINGS: EventSettings = piston_window::EventSettings {
         max_fps: 60,
         ups: 120,
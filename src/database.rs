@@ -0,0 +1,361 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::game_state::GameState;
+use crate::options::Options;
+use crate::player::Player;
+use crate::position;
+use crate::profile::{GameRecord, Tally};
+use crate::seat::Seat;
+use crate::variant::Variant;
+
+pub const DATABASE_FILE: &str = "database.txt";
+
+/// Where a game went through a position
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+struct Occurrence {
+    record: usize,
+    /// The move played next, none at the end of the game
+    next: Option<Move>,
+    /// Whether the game had the mirror image of the side with the lower key on the board
+    mirrored: bool,
+}
+
+/// A move played in a position, and how the games went for the player who played it
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Continuation {
+    pub mv: Move,
+    pub tally: Tally,
+}
+
+/// Game records from any source, indexed by every position they went through.
+/// The records are kept in the data directory in the format of `games.txt`,
+/// a position and its mirror image are looked up as one.
+#[derive(Default)]
+pub struct GameDatabase {
+    dir: Option<PathBuf>,
+    records: Vec<GameRecord>,
+    /// The lines of the records, a game is only added once
+    lines: HashSet<String>,
+    /// By the canonical key of the position
+    index: HashMap<u64, Vec<Occurrence>>,
+}
+
+impl GameDatabase {
+    pub fn open(dir: &Path) -> GameDatabase {
+        let mut database = GameDatabase { dir: Some(dir.to_path_buf()), ..GameDatabase::default() };
+
+        match fs::read_to_string(dir.join(DATABASE_FILE)) {
+            Ok(content) => {
+                for (i, line) in content.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+                    if let Err(e) = GameRecord::parse(line).and_then(|record| database.insert(record)) {
+                        eprintln!("Skipping game {} in {}: {}", i + 1, DATABASE_FILE, e);
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => eprintln!("Failed to read game database: {}", e),
+        }
+
+        database
+    }
+
+    /// Adds the record to the index, whether it wasn't in the database yet.
+    /// Records with a move against the rules are refused.
+    fn insert(&mut self, record: GameRecord) -> Result<bool, String> {
+        let line = record.to_line();
+
+        if self.lines.contains(&line) {
+            return Ok(false);
+        }
+
+        let i = self.records.len();
+
+        for (key, mirrored, next) in replay(&record)? {
+            self.index.entry(key).or_default().push(Occurrence { record: i, next, mirrored });
+        }
+
+        self.records.push(record);
+        self.lines.insert(line);
+
+        Ok(true)
+    }
+
+    /// Adds the records of a file in the format of `games.txt`, how many were new
+    pub fn import(&mut self, path: &Path) -> io::Result<usize> {
+        let content = fs::read_to_string(path)?;
+        let mut lines = vec![];
+
+        for (i, line) in content.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+            match GameRecord::parse(line).and_then(|record| self.insert(record)) {
+                Ok(true) => lines.push(self.records[self.records.len() - 1].to_line()),
+                Ok(false) => (),
+                Err(e) => eprintln!("Skipping game {} in {}: {}", i + 1, path.display(), e),
+            }
+        }
+
+        self.append(&lines)?;
+
+        Ok(lines.len())
+    }
+
+    fn append(&self, lines: &[String]) -> io::Result<()> {
+        let Some(dir) = &self.dir else { return Ok(()); };
+
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(dir)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(DATABASE_FILE))?;
+
+        writeln!(file, "{}", lines.join("\n"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Where games of the variant went through the position or its mirror image
+    fn occurrences<'a>(&'a self, game: &'a Game) -> impl Iterator<Item = &'a Occurrence> {
+        self.index.get(&game.canonical_key())
+            .into_iter()
+            .flatten()
+            .filter(|occurrence| self.records[occurrence.record].variant == game.variant)
+    }
+
+    /// The games that went through the position or its mirror image
+    pub fn games(&self, game: &Game) -> Vec<&GameRecord> {
+        let mut records: Vec<usize> = self.occurrences(game).map(|occurrence| occurrence.record).collect();
+        records.dedup();
+
+        records.into_iter().map(|i| &self.records[i]).collect()
+    }
+
+    /// The moves played in the position, the most played first. Moves played
+    /// in the mirror image are turned around to fit the game.
+    pub fn continuations(&self, game: &Game) -> Vec<Continuation> {
+        let GameState::Running(player) = game.state else { return vec![]; };
+
+        let mirrored = game.mirror_key() < game.key();
+        let mut continuations: Vec<Continuation> = vec![];
+
+        for occurrence in self.occurrences(game) {
+            let Some(mut mv) = occurrence.next else { continue; };
+
+            if occurrence.mirrored != mirrored {
+                mv = mv.mirrored(game.columns());
+            }
+
+            let outcome = self.records[occurrence.record].outcome(player);
+
+            match continuations.iter_mut().find(|continuation| continuation.mv == mv) {
+                Some(continuation) => continuation.tally.add(outcome),
+                None => {
+                    let mut tally = Tally::default();
+                    tally.add(outcome);
+                    continuations.push(Continuation { mv, tally });
+                }
+            }
+        }
+
+        continuations.sort_by_key(|continuation| std::cmp::Reverse(continuation.tally.games()));
+        continuations
+    }
+}
+
+/// The canonical key of every position of the game, whether it was the mirror
+/// image and the move played there
+fn replay(record: &GameRecord) -> Result<Vec<(u64, bool, Option<Move>)>, String> {
+    // Records don't say whether gravity was on, placements only happen without it
+    let gravity = !record.moves.iter().any(|mv| matches!(mv, Move::Place(..)));
+
+    let mut game = Game::initial(record.variant, Seat::solo(&[Player::Yellow, Player::Red])).with_gravity(gravity);
+    game.start(record.first);
+
+    let mut positions = vec![];
+
+    for (i, &mv) in record.moves.iter().enumerate() {
+        positions.push((game.canonical_key(), game.mirror_key() < game.key(), Some(mv)));
+
+        if !game.apply(mv) {
+            return Err(format!("Illegal move {} '{}'", i + 1, Move::list_text(&[mv])));
+        }
+    }
+
+    positions.push((game.canonical_key(), game.mirror_key() < game.key(), None));
+
+    Ok(positions)
+}
+
+pub struct DatabaseOptions {
+    pub data_dir: PathBuf,
+    /// The record files to import
+    pub files: Vec<PathBuf>,
+    pub variant: Variant,
+    /// The player who started, both if not given
+    pub first: Option<Player>,
+    /// The moves from the start to the position looked up
+    pub moves: Vec<Move>,
+    /// A position to look up instead of moves, with the player to move if given
+    pub position: Option<String>,
+    pub player: Option<Player>,
+    /// How many of the games are listed
+    pub games: usize,
+}
+
+impl DatabaseOptions {
+    /// Parses the files to import, `--data-dir <path>`, `--variant <name>`,
+    /// `--first <color>`, `--moves <moves>`, `--position <position>`,
+    /// `--player <color>` and `--games <count>`
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<DatabaseOptions, String> {
+        let mut options = DatabaseOptions {
+            data_dir: Options::default().data_dir,
+            files: vec![],
+            variant: Variant::Classic,
+            first: None,
+            moves: vec![],
+            position: None,
+            player: None,
+            games: 10,
+        };
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                options.files.push(PathBuf::from(arg));
+                continue;
+            }
+
+            let value = args.next().ok_or(format!("Missing value for {arg}"))?;
+
+            match arg.as_str() {
+                "--data-dir" => options.data_dir = PathBuf::from(value),
+                "--variant" => options.variant = Variant::parse(&value)?,
+                "--first" => options.first = Some(Player::parse(&value)?),
+                "--moves" => options.moves = Move::parse_list(&value)?,
+                "--position" => options.position = Some(value),
+                "--player" => options.player = Some(Player::parse(&value)?),
+                "--games" => options.games = value.parse().map_err(|_| format!("Invalid count '{value}' for {arg}"))?,
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// The games at the position looked up, one for each player who could have started or be to move
+    fn positions(&self) -> Result<Vec<Game>, String> {
+        let players = [Player::Yellow, Player::Red];
+
+        if let Some(position) = &self.position {
+            return players.into_iter()
+                .filter(|&player| self.player.is_none_or(|to_move| to_move == player))
+                .map(|player| position::game(self.variant, position, player))
+                .collect();
+        }
+
+        players.into_iter()
+            .filter(|&player| self.first.is_none_or(|first| first == player))
+            .map(|first| {
+                let gravity = !self.moves.iter().any(|mv| matches!(mv, Move::Place(..)));
+
+                let mut game = Game::initial(self.variant, Seat::solo(&players)).with_gravity(gravity);
+                game.start(first);
+
+                for (i, &mv) in self.moves.iter().enumerate() {
+                    if !game.apply(mv) {
+                        return Err(format!("Illegal move {} '{}'", i + 1, Move::list_text(&[mv])));
+                    }
+                }
+
+                Ok(game)
+            })
+            .collect()
+    }
+}
+
+/// Adds the record files to the database in the data directory
+pub fn import(options: &DatabaseOptions) {
+    let mut database = GameDatabase::open(&options.data_dir);
+
+    for file in &options.files {
+        match database.import(file) {
+            Ok(count) => println!("{}: {} new games", file.display(), count),
+            Err(e) => eprintln!("Failed to import {}: {}", file.display(), e),
+        }
+    }
+
+    println!("{} games in the database", database.len());
+}
+
+/// Prints the games through the position, and how often each move was played
+/// there and how it went for the player who played it
+pub fn query(options: &DatabaseOptions) -> Result<(), String> {
+    let database = GameDatabase::open(&options.data_dir);
+    let positions = options.positions()?;
+
+    if database.is_empty() {
+        println!("The database is empty, add games with import-games");
+        return Ok(());
+    }
+
+    let mut continuations: Vec<Continuation> = vec![];
+    let mut games = vec![];
+
+    for game in &positions {
+        for continuation in database.continuations(game) {
+            match continuations.iter_mut().find(|other| other.mv == continuation.mv) {
+                Some(other) => {
+                    other.tally.wins += continuation.tally.wins;
+                    other.tally.losses += continuation.tally.losses;
+                    other.tally.draws += continuation.tally.draws;
+                }
+                None => continuations.push(continuation),
+            }
+        }
+
+        games.extend(database.games(game));
+    }
+
+    continuations.sort_by_key(|continuation| std::cmp::Reverse(continuation.tally.games()));
+
+    println!("{} of {} games reach the position", games.len(), database.len());
+
+    if !continuations.is_empty() {
+        println!("Move\tGames\tWon\tDrawn\tLost");
+    }
+
+    for Continuation { mv, tally } in &continuations {
+        let percent = |count: u32| 100 * count / tally.games();
+
+        println!("{}\t{}\t{}%\t{}%\t{}%",
+                 Move::list_text(&[*mv]),
+                 tally.games(),
+                 percent(tally.wins),
+                 percent(tally.draws),
+                 percent(tally.losses));
+    }
+
+    for record in games.iter().take(options.games) {
+        let result = record.winner.map_or(String::from("draw"), |winner| format!("{} won", winner.text()));
+
+        println!("{} - {}, {}: {}", record.yellow, record.red, result, Move::list_text(&record.moves));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use arboard::Clipboard;
+
+use crate::game::Game;
+use crate::piece::Piece;
+use crate::player::Player;
+use crate::position;
+
+/// Sets up a position by hand on the board of a game that hasn't started yet,
+/// to be played from once it is valid
+pub struct Editor {
+    pub to_move: Player,
+    /// What the last import or export did
+    pub message: Option<String>,
+}
+
+impl Editor {
+    pub fn new(game: &Game) -> Editor {
+        Editor { to_move: game.players[0], message: None }
+    }
+
+    /// Turns the cell to the coin of the next player, or empty after the last one.
+    /// Backwards goes the other way round.
+    pub fn cycle(&mut self, game: &mut Game, col: usize, row: usize, backwards: bool) {
+        let mut cells: Vec<Option<Piece>> = vec![None];
+        cells.extend(game.players.iter().map(|&player| Some(Piece::coin(player))));
+
+        if backwards {
+            cells.reverse();
+        }
+
+        let i = cells.iter().position(|&cell| cell == game.board[col][row]).unwrap_or(0);
+
+        game.set_cell(col, row, cells[(i + 1) % cells.len()]);
+        self.message = None;
+    }
+
+    pub fn clear(&mut self, game: &mut Game) {
+        game.set_board(game.rules().initial_board(game.players.len()));
+        self.message = None;
+    }
+
+    /// Gives the move to the next player
+    pub fn switch_sides(&mut self, game: &Game) {
+        self.to_move = self.to_move.next_in(&game.players);
+        self.message = None;
+    }
+
+    /// Why the game can't start from the position, if it can't
+    pub fn problem(&self, game: &Game) -> Option<String> {
+        if let Some(col) = position::floating_column(&game.board).filter(|_| game.gravity) {
+            return Some(format!("Coin floating in column {}", col + 1));
+        }
+
+        if let Some(player) = game.rules().winners(game).0.first() {
+            return Some(format!("{} already has a line", player.text()));
+        }
+
+        if game.board.iter().flatten().all(|cell| cell.is_some()) {
+            return Some(String::from("The board is full"));
+        }
+
+        Editor::first_player(game, &game.board, self.to_move).err()
+    }
+
+    /// Starts the game from the position with the player to move, returns who started it
+    pub fn play(&self, game: &mut Game) -> Result<Player, String> {
+        if let Some(problem) = self.problem(game) {
+            return Err(problem);
+        }
+
+        game.start(self.to_move);
+
+        Editor::first_player(game, &game.board, self.to_move)
+    }
+
+    /// Who made the first move to reach the board with the player to move. When every
+    /// turn adds a coin, the player to move has as many coins as the other player if
+    /// they started, or one fewer.
+    fn first_player(game: &Game, board: &[Vec<Option<Piece>>], to_move: Player) -> Result<Player, String> {
+        if !game.rules().one_coin_per_turn() {
+            return Ok(to_move);
+        }
+
+        let other = to_move.next_in(&game.players);
+        let count = |player: Player| board.iter().flatten().flatten().filter(|piece| piece.player == player).count();
+
+        match (count(to_move), count(other)) {
+            (mine, theirs) if mine == theirs => Ok(to_move),
+            (mine, theirs) if mine + 1 == theirs => Ok(other),
+            _ => Err(format!("The coin counts don't fit {} to move", to_move.text())),
+        }
+    }
+
+    /// Copies the position to the clipboard, tab separated like a book entry:
+    /// the board and the player to move
+    pub fn export(&mut self, game: &Game, clipboard: Option<&mut Clipboard>) {
+        let line = format!("{}\t{}", position::text(&game.board), self.to_move.text());
+
+        // Printed as well, in case there is no clipboard
+        println!("{line}");
+
+        let Some(clipboard) = clipboard else {
+            self.message = Some(String::from("No clipboard, the position was printed"));
+            return;
+        };
+
+        self.message = Some(match clipboard.set_text(line) {
+            Ok(()) => String::from("Copied the position"),
+            Err(e) => {
+                eprintln!("Failed to copy position: {}", e);
+                String::from("Failed to copy, the position was printed")
+            }
+        });
+    }
+
+    /// Sets up the position on the clipboard, the board has to be the size of the game's
+    pub fn import(&mut self, game: &mut Game, clipboard: Option<&mut Clipboard>) {
+        let Some(clipboard) = clipboard else {
+            self.message = Some(String::from("No clipboard to paste from"));
+            return;
+        };
+
+        let text = match clipboard.get_text() {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("Failed to paste position: {}", e);
+                self.message = Some(String::from("Failed to paste"));
+                return;
+            }
+        };
+
+        // The reason a pasted line can't be used is shown, it's likely a typo
+        self.message = Some(match Editor::set_up(game, text.trim()) {
+            Ok(player) => {
+                self.to_move = player;
+                String::from("Pasted the position")
+            }
+            Err(e) => e,
+        });
+    }
+
+    /// Puts the board of an exported line on the game, the player to move if it fits
+    fn set_up(game: &mut Game, line: &str) -> Result<Player, String> {
+        let [text, player] = line.split('\t').collect::<Vec<&str>>()[..] else {
+            return Err(String::from("Expected a position and the player to move"));
+        };
+
+        // Floating coins are allowed in the editor until the game starts
+        let board = position::parse(text, false)?;
+        let player = Player::parse(player)?;
+
+        if (board.len(), board[0].len()) != (game.columns(), game.rows()) {
+            return Err(format!("Expected a {}x{} board", game.columns(), game.rows()));
+        }
+
+        let mut players = board.iter().flatten().flatten().map(|piece| piece.player).chain([player]);
+
+        if let Some(other) = players.find(|other| !game.players.contains(other)) {
+            return Err(format!("{} doesn't play in this game", other.text()));
+        }
+
+        Editor::first_player(game, &board, player)?;
+
+        game.set_board(board);
+
+        Ok(player)
+    }
+}
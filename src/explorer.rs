@@ -0,0 +1,67 @@
+use std::cmp::Reverse;
+
+use crate::ai::{self, Difficulty};
+use crate::database::GameDatabase;
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::profile::Tally;
+
+/// The engine looks at every move as deep as on medium, so the panel keeps up with the game
+const EXPLORER_DIFFICULTY: Difficulty = Difficulty::Medium;
+
+/// A legal move, how it went in the database for the player who played it and what the engine thinks of it
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct ExplorerRow {
+    pub mv: Move,
+    pub tally: Tally,
+    pub score: i32,
+}
+
+impl ExplorerRow {
+    pub const HEADER: &'static str = "Move Games  Won Drawn Lost  Score";
+
+    pub fn text(&self) -> String {
+        let games = self.tally.games();
+        let percent = |count: u32| match games {
+            0 => String::from("-"),
+            games => format!("{}%", 100 * count / games),
+        };
+
+        format!("{:<4} {:>5} {:>4} {:>5} {:>4} {:>6}",
+                Move::list_text(&[self.mv]),
+                games,
+                percent(self.tally.wins),
+                percent(self.tally.draws),
+                percent(self.tally.losses),
+                ai::score_text(self.score))
+    }
+}
+
+/// The opening explorer for one position
+pub struct Explorer {
+    /// The key of the position the rows are for
+    pub key: u64,
+    /// The most played moves first, then the engine's favourites
+    pub rows: Vec<ExplorerRow>,
+}
+
+impl Explorer {
+    pub fn new(game: &Game, database: &GameDatabase) -> Explorer {
+        let continuations = database.continuations(game);
+
+        let mut rows: Vec<ExplorerRow> = ai::move_scores(game, EXPLORER_DIFFICULTY).into_iter()
+            .map(|(mv, score)| {
+                let tally = continuations.iter()
+                    .find(|continuation| continuation.mv == mv)
+                    .map_or(Tally::default(), |continuation| continuation.tally);
+
+                ExplorerRow { mv, tally, score }
+            })
+            .collect();
+
+        rows.sort_by_key(|row| (Reverse(row.tally.games()), Reverse(row.score)));
+
+        Explorer { key: game.key(), rows }
+    }
+}
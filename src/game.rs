@@ -1,171 +1,578 @@
-use crate::constants::{COLUMNS, ROWS};
-use crate::game_state::GameState;
+use std::collections::HashMap;
+
+use crate::game_move::Move;
+use crate::game_state::{DrawReason, GameState, WinReason};
+use crate::piece::{Piece, PieceKind};
 use crate::player::Player;
+use crate::rules::ruleset::Ruleset;
+use crate::seat::Seat;
+use crate::variant::Variant;
+use crate::zobrist;
+
+/// What a move changed, so it can be taken back
+#[derive(Clone)]
+struct Undo {
+    /// The cells the move changed, with what was on them before
+    cells: Vec<(usize, usize, Option<Piece>)>,
+    state: GameState,
+    turn: usize,
+    draw_offer: Option<Player>,
+    /// The key of the position before the move, to find repetitions
+    key: u64,
+    holding: Option<Piece>,
+    /// The counts of the player who moved, rules only change their own
+    captured: u32,
+    powers: Vec<PieceKind>,
+    rotations: u32,
+}
 
 #[derive(Clone)]
 pub struct Game {
-    pub board: Vec<Vec<Option<Player>>>,
+    pub board: Vec<Vec<Option<Piece>>>,
     pub state: GameState,
+    pub draw_offer: Option<Player>,
+    pub moves: Vec<Move>,
+    pub variant: Variant,
+    /// Coins each player has captured in Pop Ten
+    pub captured: HashMap<Player, u32>,
+    /// Power coins each player has left in Power Up
+    pub powers: HashMap<Player, Vec<PieceKind>>,
+    /// Board rotations each player has left in Rotation
+    pub rotations: HashMap<Player, u32>,
+    /// A coin the player to move popped in Pop Ten and has to put back on top of a column
+    pub holding: Option<Piece>,
+    /// Every color in the game, in seat order
+    pub players: Vec<Player>,
+    /// Everyone at the table in turn order, in team play two seats share a color
+    pub seats: Vec<Seat>,
+    /// The index of the seat to move
+    pub turn: usize,
+    /// Players who resigned or ran out of time, in that order
+    pub eliminated: Vec<Player>,
+    /// Without gravity coins are placed on any empty cell instead of dropped
+    pub gravity: bool,
+    /// Zobrist hash of the board, kept up to date on every change of a cell
+    pub hash: u64,
+    /// The hash of the board seen in a mirror
+    pub mirror_hash: u64,
+    /// One entry per move, to take it back
+    history: Vec<Undo>,
 }
 
 impl Game {
-    pub fn initial() -> Game {
+    pub fn initial(variant: Variant, seats: Vec<Seat>) -> Game {
+        let mut players: Vec<Player> = vec![];
+
+        for seat in &seats {
+            if !players.contains(&seat.player) {
+                players.push(seat.player);
+            }
+        }
+
+        let rules = variant.rules();
+        let board = rules.initial_board(players.len());
+        let (hash, mirror_hash) = zobrist::board(&board);
+
         Self {
-            board: vec![vec![None; 6]; 7],
+            board,
             state: GameState::initial(),
+            draw_offer: None,
+            moves: vec![],
+            variant,
+            captured: HashMap::new(),
+            powers: players.iter().map(|&player| (player, rules.powers())).collect(),
+            rotations: players.iter().map(|&player| (player, rules.rotations())).collect(),
+            holding: None,
+            players,
+            seats,
+            turn: 0,
+            eliminated: vec![],
+            gravity: true,
+            hash,
+            mirror_hash,
+            history: vec![],
         }
     }
 
-    pub fn update_state(&mut self) {
-        for column in &self.board {
-            let mut count = 0;
-            let mut cell_owner = None;
-            for cell in column {
-                if *cell != None && *cell == cell_owner {
-                    count += 1;
-                    if count >= 4 {
-                        self.state = GameState::Win(cell.clone().unwrap());
-                        return;
-                    }
-                } else {
-                    count = 1;
-                    cell_owner = (*cell).clone();
-                }
+    /// Turns gravity off for variants that can be played without it
+    pub fn with_gravity(mut self, gravity: bool) -> Game {
+        self.gravity = gravity || !self.rules().gravity_optional();
+        self
+    }
+
+    pub fn rules(&self) -> &'static dyn Ruleset {
+        self.variant.rules()
+    }
+
+    /// Starts the game at the first seat of the player
+    pub fn start(&mut self, first: Player) {
+        self.turn = self.seats.iter().position(|seat| seat.player == first).unwrap_or(0);
+        self.state = self.state.next(first);
+    }
+
+    /// The seat to move, while the game is running
+    pub fn seat(&self) -> Option<Seat> {
+        match self.state {
+            GameState::Running(_) => self.seats.get(self.turn).copied(),
+            _ => None
+        }
+    }
+
+    /// The players still in the game, in seat order
+    pub fn active_players(&self) -> Vec<Player> {
+        self.players.iter()
+            .filter(|player| !self.eliminated.contains(player))
+            .copied()
+            .collect()
+    }
+
+    /// The first player still in the game seated after `player`
+    pub fn next_player(&self, player: Player) -> Player {
+        let mut next = player.next_in(&self.players);
+
+        while self.eliminated.contains(&next) && next != player {
+            next = next.next_in(&self.players);
+        }
+
+        next
+    }
+
+    /// The seats still in the game, starting with the one to move
+    pub fn turn_order(&self) -> Vec<Seat> {
+        let mut order = self.seats.clone();
+
+        if let GameState::Running(_) = self.state {
+            order.rotate_left(self.turn);
+        }
+
+        order.retain(|seat| !self.eliminated.contains(&seat.player));
+        order
+    }
+
+    /// Passes the turn to the next seat whose player is still in the game
+    fn advance(&mut self) {
+        for _ in 0..self.seats.len() {
+            self.turn = (self.turn + 1) % self.seats.len();
+
+            if !self.eliminated.contains(&self.seats[self.turn].player) {
+                break;
             }
         }
 
-        for row in 0..ROWS {
-            let mut count = 0;
-            let mut cell_owner = None;
-            for col in 0..COLUMNS {
-                let cell = &self.board[col as usize][row as usize];
+        self.state = GameState::Running(self.seats[self.turn].player);
+    }
 
-                if *cell != None && *cell == cell_owner {
-                    count += 1;
-                    if count >= 4 {
-                        self.state = GameState::Win(cell.clone().unwrap());
-                        return;
-                    }
-                } else {
-                    count = 1;
-                    cell_owner = (*cell).clone();
-                }
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let GameState::Running(player) = self.state else { return vec![]; };
+
+        self.rules().legal_moves(self, player)
+    }
+
+    /// Plays a legal move for the player to move
+    pub fn apply(&mut self, mv: Move) -> bool {
+        let GameState::Running(player) = self.state else { return false; };
+
+        if !self.legal_moves().contains(&mv) {
+            return false;
+        }
+
+        self.history.push(Undo {
+            cells: vec![],
+            state: self.state.clone(),
+            turn: self.turn,
+            draw_offer: self.draw_offer,
+            key: self.key(),
+            holding: self.holding,
+            captured: self.captured(player),
+            powers: self.powers(player).to_vec(),
+            rotations: self.rotations(player),
+        });
+
+        self.rules().apply(self, player, mv);
+        self.moves.push(mv);
+        self.update_state();
+
+        debug_assert_eq!((self.hash, self.mirror_hash), zobrist::board(&self.board), "Board changed without set_cell");
+
+        true
+    }
+
+    /// Takes back the last move, whether there was one
+    pub fn undo(&mut self) -> bool {
+        let Some(undo) = self.history.pop() else { return false; };
+
+        self.moves.pop();
+
+        for &(col, row, piece) in undo.cells.iter().rev() {
+            self.put(col, row, piece);
+        }
+
+        if let GameState::Running(player) = undo.state {
+            self.captured.insert(player, undo.captured);
+            self.powers.insert(player, undo.powers);
+            self.rotations.insert(player, undo.rotations);
+        }
+
+        self.state = undo.state;
+        self.turn = undo.turn;
+        self.draw_offer = undo.draw_offer;
+        self.holding = undo.holding;
+
+        debug_assert_eq!((self.hash, self.mirror_hash), zobrist::board(&self.board), "Board changed without set_cell");
+
+        true
+    }
+
+    /// Puts the piece on the cell while a move is played, so undo can take it back
+    pub fn set_cell(&mut self, col: usize, row: usize, piece: Option<Piece>) {
+        let old = self.board[col][row];
+
+        if let Some(undo) = self.history.last_mut() {
+            undo.cells.push((col, row, old));
+        }
+
+        self.put(col, row, piece);
+    }
+
+    /// Replaces a whole column while a move is played
+    pub fn set_column(&mut self, col: usize, column: Vec<Option<Piece>>) {
+        for (row, piece) in column.into_iter().enumerate() {
+            if self.board[col][row] != piece {
+                self.set_cell(col, row, piece);
             }
         }
+    }
 
-        for start_col in 0..COLUMNS {
-            let mut count = 0;
-            let mut cell_owner = None;
-            let mut col = start_col;
-            let mut row = 0;
+    /// Sets up a new position, the moves before it can't be taken back
+    pub fn set_board(&mut self, board: Vec<Vec<Option<Piece>>>) {
+        (self.hash, self.mirror_hash) = zobrist::board(&board);
+        self.board = board;
+        self.history.clear();
+    }
 
-            while col < COLUMNS && row < ROWS {
-                let cell = &self.board[col as usize][row as usize];
+    /// Changes the cell and both hashes
+    fn put(&mut self, col: usize, row: usize, piece: Option<Piece>) {
+        let mirror_col = self.columns() - 1 - col;
 
-                if *cell != None && *cell == cell_owner {
-                    count += 1;
-                    if count >= 4 {
-                        self.state = GameState::Win(cell.clone().unwrap());
-                        return;
-                    }
-                } else {
-                    count = 1;
-                    cell_owner = (*cell).clone();
-                }
+        for old in self.board[col][row].iter().chain(piece.iter()) {
+            self.hash ^= zobrist::piece(col, row, *old);
+            self.mirror_hash ^= zobrist::piece(mirror_col, row, *old);
+        }
+
+        self.board[col][row] = piece;
+    }
+
+    /// The hash of the whole position: the board, the player to move and the
+    /// captured coins, power coins and rotations everyone has
+    pub fn key(&self) -> u64 {
+        self.hash ^ self.extras_key()
+    }
+
+    /// The key of the position seen in a mirror
+    pub fn mirror_key(&self) -> u64 {
+        self.mirror_hash ^ self.extras_key()
+    }
+
+    /// How often the position came up before in the game, with the same player to move
+    pub fn repetitions(&self) -> usize {
+        let key = self.key();
+
+        self.history.iter().filter(|undo| undo.key == key).count()
+    }
 
-                col += 1;
-                row += 1;
+    /// The same for a position and its mirror image
+    pub fn canonical_key(&self) -> u64 {
+        self.key().min(self.mirror_key())
+    }
+
+    /// The part of the key that doesn't depend on where the coins are
+    fn extras_key(&self) -> u64 {
+        let mut key = match self.state {
+            GameState::Running(player) => zobrist::side_to_move(player),
+            _ => 0,
+        };
+
+        for &player in &self.players {
+            key ^= zobrist::captured(player, self.captured(player)) ^ zobrist::rotations(player, self.rotations(player));
+
+            for &kind in self.powers(player) {
+                key ^= zobrist::power(player, kind);
             }
         }
 
-        for start_row in 0..ROWS {
-            let mut count = 0;
-            let mut cell_owner = None;
-            let mut col = 0;
-            let mut row = start_row;
+        if let Some(piece) = self.holding {
+            key ^= zobrist::holding(piece.player);
+        }
 
-            while col < COLUMNS && row < ROWS {
-                let cell = &self.board[col as usize][row as usize];
+        key
+    }
 
-                if *cell != None && *cell == cell_owner {
-                    count += 1;
-                    if count >= 4 {
-                        self.state = GameState::Win(cell.clone().unwrap());
-                        return;
-                    }
-                } else {
-                    count = 1;
-                    cell_owner = (*cell).clone();
-                }
+    pub fn columns(&self) -> usize {
+        self.board.len()
+    }
 
-                col += 1;
-                row += 1;
+    pub fn rows(&self) -> usize {
+        self.board[0].len()
+    }
+
+    pub fn free_row(&self, col: usize) -> Option<usize> {
+        self.board.get(col)?.iter().position(|cell| cell.is_none())
+    }
+
+    /// The highest piece in the column
+    pub fn top(&self, col: usize) -> Option<Piece> {
+        self.board.get(col)?.iter().flatten().last().copied()
+    }
+
+    pub fn rotations(&self, player: Player) -> u32 {
+        *self.rotations.get(&player).unwrap_or(&0)
+    }
+
+    pub fn powers(&self, player: Player) -> &[PieceKind] {
+        self.powers.get(&player).map_or(&[], |powers| powers)
+    }
+
+    pub fn captured(&self, player: Player) -> u32 {
+        *self.captured.get(&player).unwrap_or(&0)
+    }
+
+    pub fn resign(&mut self, player: Player) {
+        self.eliminate(player, WinReason::Resignation);
+    }
+
+    pub fn time_out(&mut self, player: Player) {
+        self.eliminate(player, WinReason::Timeout);
+    }
+
+    /// Takes the player out of the game, the last player left wins
+    fn eliminate(&mut self, player: Player, reason: WinReason) {
+        let GameState::Running(cur_player) = self.state else { return; };
+
+        if self.eliminated.contains(&player) {
+            return;
+        }
+
+        self.eliminated.push(player);
+
+        if let [winner] = self.active_players()[..] {
+            self.state = GameState::Win { player: winner, reason };
+        } else if cur_player == player {
+            self.advance();
+        }
+    }
+
+    /// Offers a draw, or accepts one if the opponent has already offered.
+    /// The offer stands until the opponent answers it with a move. Draws
+    /// can only be agreed on by the last two players in the game.
+    pub fn offer_draw(&mut self, player: Player) {
+        if self.active_players().len() != 2 {
+            return;
+        }
+
+        if let GameState::Running(_) = self.state {
+            if self.draw_offer == Some(self.next_player(player)) {
+                self.state = GameState::Draw(DrawReason::Agreement);
+            } else {
+                self.draw_offer = Some(player);
             }
         }
+    }
 
-        for start_col in (0..COLUMNS).rev() {
-            let mut count = 0;
-            let mut cell_owner = None;
-            let mut col = start_col;
-            let mut row = 0;
+    pub fn accept_draw(&mut self, player: Player) {
+        if self.draw_offer.is_some_and(|offer| offer != player) {
+            self.offer_draw(player);
+        }
+    }
 
-            while col < COLUMNS && row < ROWS {
-                let cell = &self.board[col as usize][row as usize];
-                if *cell != None && *cell == cell_owner {
-                    count += 1;
-                    if count >= 4 {
-                        self.state = GameState::Win(cell.clone().unwrap());
-                        return;
-                    }
-                } else {
-                    count = 1;
-                    cell_owner = (*cell).clone();
-                }
+    pub fn update_state(&mut self) {
+        // Moving instead of accepting declines the opponent's offer
+        if let GameState::Running(cur_player) = &self.state {
+            if self.draw_offer.is_some_and(|offer| offer != *cur_player) {
+                self.draw_offer = None;
+            }
+        }
 
-                if col > 0 {
-                    col -= 1;
-                }
-                row += 1;
+        let (mut winners, reason) = self.rules().winners(self);
+
+        // The coins of eliminated players stay on the board but can't win
+        winners.retain(|player| !self.eliminated.contains(player));
+
+        // A pop or the fall after a rotation can complete lines for several players, the mover wins then
+        let winner = match &self.state {
+            GameState::Running(cur_player) if winners.contains(cur_player) => Some(*cur_player),
+            _ => winners.first().copied()
+        };
+
+        if let Some(player) = winner {
+            self.state = GameState::Win { player, reason };
+            return;
+        }
+
+        if let GameState::Running(_) = &self.state {
+            // Some moves, like the x2 coin of Power Up, give the player another move,
+            // a popped coin of Pop Ten is put back before the turn passes
+            if !self.moves.last().is_some_and(|&mv| self.rules().moves_again(mv)) && self.holding.is_none() {
+                self.advance();
+            }
+
+            if self.legal_moves().is_empty() {
+                self.state = GameState::Draw(DrawReason::BoardFull);
+            } else if self.repetitions() >= 2 {
+                // Pops and rotations can bring a position back, the third time ends the game
+                self.state = GameState::Draw(DrawReason::Repetition);
             }
         }
+    }
 
-        for start_row in (0..ROWS).rev() {
-            let mut count = 0;
-            let mut cell_owner = None;
-            let mut col = 0;
-            let mut row = start_row;
+    /// The player the cell counts for in a line, walls count for nobody
+    pub fn cell(&self, col: i32, row: i32) -> Option<Player> {
+        let col = if self.rules().wraps() { col.rem_euclid(self.columns() as i32) } else { col };
 
-            while col < COLUMNS && row < ROWS {
-                let cell = &self.board[col as usize][row as usize];
-                if *cell != None && *cell == cell_owner {
-                    count += 1;
-                    if count >= 4 {
-                        self.state = GameState::Win(cell.clone().unwrap());
-                        return;
-                    }
-                } else {
-                    count = 1;
-                    cell_owner = (*cell).clone();
-                }
+        if col < 0 || row < 0 {
+            return None;
+        }
 
-                if col > 0 {
-                    col -= 1;
-                }
-                row += 1;
+        let piece = (*self.board.get(col as usize)?.get(row as usize)?)?;
+
+        Some(piece.player).filter(|_| piece.kind != PieceKind::Wall)
+    }
+
+    /// Whether `player` can win with at most `moves` more moves of their own,
+    /// whatever everyone else plays
+    pub fn forced_win(&self, player: Player, moves: u32) -> bool {
+        match self.state {
+            GameState::Win { player: winner, .. } => winner == player,
+            GameState::Starting | GameState::Draw(_) => false,
+            GameState::Running(mover) if mover == player => {
+                moves > 0 && self.legal_moves().into_iter().any(|mv| self.after(mv).forced_win(player, moves - 1))
+            }
+            GameState::Running(_) => {
+                self.legal_moves().into_iter().all(|mv| self.after(mv).forced_win(player, moves))
             }
         }
+    }
+
+    /// The moves that force a win for the player to move within `moves` of their own moves
+    pub fn winning_moves(&self, moves: u32) -> Vec<Move> {
+        let GameState::Running(player) = self.state else { return vec![]; };
+
+        if moves == 0 {
+            return vec![];
+        }
+
+        self.legal_moves().into_iter()
+            .filter(|&mv| self.after(mv).forced_win(player, moves - 1))
+            .collect()
+    }
+
+    /// A copy of the game with the move played
+    fn after(&self, mv: Move) -> Game {
+        let mut game = self.clone();
+        game.apply(mv);
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::position;
+
+    const GAMES: usize = 20;
+
+    /// The incremental hashes must match hashing the board from scratch, and the
+    /// mirror hash must match the hash of the mirrored board
+    fn assert_hashes(game: &Game) {
+        assert_eq!((game.hash, game.mirror_hash), zobrist::board(&game.board), "{}", Move::list_text(&game.moves));
+        assert_eq!(game.mirror_hash, zobrist::board(&position::mirrored(&game.board)).0, "{}", Move::list_text(&game.moves));
+    }
+
+    #[test]
+    fn hashes_follow_random_games_and_undo() {
+        let mut rng = StdRng::seed_from_u64(4);
+
+        for variant in Variant::ALL {
+            for gravity in [true, false] {
+                for _ in 0..GAMES {
+                    let mut game = Game::initial(variant, Seat::solo(&[Player::Yellow, Player::Red])).with_gravity(gravity);
+                    game.start(Player::Yellow);
 
-        for col in &self.board {
-            for cell in col {
-                if let None = cell {
-                    if let GameState::Running(cur_player) = &self.state {
-                        self.state = GameState::Running(cur_player.op());
-                        return;
+                    let start = (game.hash, game.mirror_hash, game.key());
+
+                    while let Some(&mv) = game.legal_moves().choose(&mut rng) {
+                        assert!(game.apply(mv));
+                        assert_hashes(&game);
+                    }
+
+                    assert!(!matches!(game.state, GameState::Running(_)));
+
+                    while game.undo() {
+                        assert_hashes(&game);
                     }
+
+                    assert!(game.moves.is_empty());
+                    assert_eq!((game.hash, game.mirror_hash, game.key()), start);
                 }
             }
         }
+    }
+
+    #[test]
+    fn popping_back_and_forth_draws_by_repetition() {
+        let mut game = Game::initial(Variant::PopOut, Seat::solo(&[Player::Yellow, Player::Red]));
+        game.start(Player::Yellow);
+
+        let start = game.key();
+        let cycle = [Move::Drop(0), Move::Drop(6), Move::Pop(0), Move::Pop(6)];
+
+        for mv in cycle {
+            assert!(game.apply(mv));
+        }
+
+        assert_eq!(game.key(), start);
+        assert_eq!(game.repetitions(), 1);
+        assert!(matches!(game.state, GameState::Running(Player::Yellow)));
+
+        for mv in cycle {
+            assert!(game.apply(mv));
+        }
+
+        assert!(matches!(game.state, GameState::Draw(DrawReason::Repetition)));
+
+        assert!(game.undo());
+        // Red to move after Yellow's pop, that came up once in the first round
+        assert!(matches!(game.state, GameState::Running(Player::Red)));
+        assert_eq!(game.repetitions(), 1);
+    }
+
+    #[test]
+    fn pop_ten_puts_popped_coins_back() {
+        let mut game = Game::initial(Variant::PopTen, Seat::solo(&[Player::Yellow, Player::Red]));
+        game.start(Player::Yellow);
+
+        for mv in [Move::Drop(0), Move::Drop(6), Move::Pop(0)] {
+            assert!(game.apply(mv));
+        }
+
+        // Not in a line, so Yellow keeps the coin and has to drop it
+        assert_eq!(game.holding, Some(Piece::coin(Player::Yellow)));
+        assert!(matches!(game.state, GameState::Running(Player::Yellow)));
+        assert!(game.legal_moves().iter().all(|mv| matches!(mv, Move::Drop(_))));
+        assert_eq!(game.captured(Player::Yellow), 0);
+
+        assert!(game.apply(Move::Drop(3)));
+        assert_eq!(game.holding, None);
+        assert_eq!(game.board[3][0], Some(Piece::coin(Player::Yellow)));
+        assert!(matches!(game.state, GameState::Running(Player::Red)));
 
-        self.state = GameState::Draw;
+        assert!(game.undo());
+        assert_eq!(game.holding, Some(Piece::coin(Player::Yellow)));
+        assert!(game.undo());
+        assert_eq!(game.holding, None);
+        assert_eq!(game.board[0][0], Some(Piece::coin(Player::Yellow)));
     }
 }
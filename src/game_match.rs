@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::game_state::GameState;
+use crate::player::Player;
+
+/// A best-of-N match. The starting player moves on by one seat every round.
+#[derive(Debug)]
+pub struct Match {
+    pub best_of: u32,
+    pub players: Vec<Player>,
+    wins: HashMap<Player, u32>,
+    draws: u32,
+    starting: Player,
+}
+
+impl Match {
+    /// A match whose first round is started by `first`
+    pub fn new(best_of: u32, players: Vec<Player>, first: Player) -> Self {
+        Self {
+            best_of,
+            players,
+            wins: HashMap::new(),
+            draws: 0,
+            starting: first,
+        }
+    }
+
+    pub fn record(&mut self, state: &GameState) {
+        match state {
+            GameState::Win { player, .. } => *self.wins.entry(*player).or_insert(0) += 1,
+            GameState::Draw(_) => self.draws += 1,
+            _ => return
+        }
+
+        self.starting = self.starting.next_in(&self.players);
+    }
+
+    pub fn wins(&self, player: Player) -> u32 {
+        *self.wins.get(&player).unwrap_or(&0)
+    }
+
+    pub fn draws(&self) -> u32 {
+        self.draws
+    }
+
+    pub fn rounds_played(&self) -> u32 {
+        self.wins.values().sum::<u32>() + self.draws
+    }
+
+    pub fn starting_player(&self) -> Player {
+        self.starting
+    }
+
+    /// The match is decided once a player can no longer be caught up with
+    pub fn is_over(&self) -> bool {
+        self.wins.values().any(|&wins| wins > self.best_of / 2) || self.rounds_played() >= self.best_of
+    }
+
+    /// The player with the most wins, if no one shares the lead
+    pub fn winner(&self) -> Option<Player> {
+        let most = self.players.iter().map(|&player| self.wins(player)).max()?;
+        let mut leaders = self.players.iter().filter(|&&player| self.wins(player) == most);
+
+        match (leaders.next(), leaders.next()) {
+            (Some(&leader), None) => Some(leader),
+            _ => None,
+        }
+    }
+
+    /// Wins in seat order, e.g. `2-1`
+    pub fn text(&self) -> String {
+        self.players.iter()
+            .map(|&player| self.wins(player).to_string())
+            .collect::<Vec<String>>()
+            .join("-")
+    }
+}
@@ -0,0 +1,89 @@
+use crate::piece::PieceKind;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Move {
+    Drop(usize),
+    /// Removes the player's own coin from the bottom of the column
+    Pop(usize),
+    /// Drops one of the player's power coins
+    Power(PieceKind, usize),
+    /// Puts a coin on an empty cell of a board without gravity, as column and row
+    Place(usize, usize),
+    /// Turns the board a quarter counterclockwise, the coins fall to the new bottom
+    RotateLeft,
+    /// Turns the board a quarter clockwise
+    RotateRight,
+}
+
+impl Move {
+    /// The column the move is played in, rotations turn the whole board
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            Move::Drop(col) | Move::Pop(col) | Move::Power(_, col) | Move::Place(col, _) => Some(*col),
+            Move::RotateLeft | Move::RotateRight => None,
+        }
+    }
+
+    /// The same move on the board seen in a mirror
+    pub fn mirrored(&self, columns: usize) -> Move {
+        let col = |col: &usize| columns - 1 - col;
+
+        match self {
+            Move::Drop(c) => Move::Drop(col(c)),
+            Move::Pop(c) => Move::Pop(col(c)),
+            Move::Power(kind, c) => Move::Power(*kind, col(c)),
+            Move::Place(c, row) => Move::Place(col(c), *row),
+            Move::RotateLeft => Move::RotateRight,
+            Move::RotateRight => Move::RotateLeft,
+        }
+    }
+
+    /// Drops as 1-based columns, pops prefixed with `p`, power coins with
+    /// their letter, placements with `@` before column and row and rotations
+    /// as `l` or `r`, e.g. `44p3a5@12r`
+    pub fn list_text(moves: &[Move]) -> String {
+        moves.iter()
+            .map(|mv| match mv {
+                Move::Drop(col) => format!("{}", col + 1),
+                Move::Pop(col) => format!("p{}", col + 1),
+                Move::Power(kind, col) => format!("{}{}", kind.letter(), col + 1),
+                Move::Place(col, row) => format!("@{}{}", col + 1, row + 1),
+                Move::RotateLeft => String::from("l"),
+                Move::RotateRight => String::from("r"),
+            })
+            .collect()
+    }
+
+    pub fn parse_list(text: &str) -> Result<Vec<Move>, String> {
+        let mut moves = vec![];
+        let mut chars = text.chars();
+
+        // A 1-based digit following the prefix
+        let index = |prefix: char, chars: &mut std::str::Chars| match chars.next().and_then(|c| c.to_digit(10)) {
+            Some(digit) if digit > 0 => Ok(digit as usize - 1),
+            _ => Err(format!("Missing column after '{prefix}'")),
+        };
+
+        while let Some(c) = chars.next() {
+            let mv = match (c, c.to_digit(10)) {
+                (_, Some(col)) if col > 0 => Move::Drop(col as usize - 1),
+                ('p', _) => Move::Pop(index(c, &mut chars)?),
+                ('@', _) => Move::Place(index(c, &mut chars)?, index(c, &mut chars)?),
+                ('l', _) => Move::RotateLeft,
+                ('r', _) => Move::RotateRight,
+                (_, None) => match PieceKind::from_letter(c) {
+                    Some(kind) => Move::Power(kind, index(c, &mut chars)?),
+                    None => return Err(format!("Invalid move '{c}'")),
+                },
+                _ => return Err(format!("Invalid move '{c}'")),
+            };
+
+            moves.push(mv);
+        }
+
+        Ok(moves)
+    }
+}
@@ -1,23 +1,45 @@
 use crate::player::Player;
 
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum WinReason {
+    FourInARow,
+    Resignation,
+    Timeout,
+    Captures,
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum DrawReason {
+    BoardFull,
+    Agreement,
+    /// The same position came up for the third time
+    Repetition,
+}
+
 #[derive(Clone)]
 #[derive(Debug)]
 pub enum GameState {
     Starting,
     Running(Player),
-    Win(Player),
-    Draw,
+    Win { player: Player, reason: WinReason },
+    Draw(DrawReason),
 }
 
 impl GameState {
     pub fn initial() -> GameState {
         Self::Starting
     }
-    pub fn next(&self) -> GameState {
+    pub fn next(&self, first: Player) -> GameState {
         match self {
-            GameState::Starting => GameState::Running(Player::Yellow),
+            GameState::Starting => GameState::Running(first),
             GameState::Running(p) => GameState::Running(p.op()),
-            GameState::Win(_) | GameState::Draw => GameState::Starting
+            GameState::Win { .. } | GameState::Draw(_) => GameState::Starting
         }
     }
 }
@@ -5,10 +5,15 @@ use gfx_graphics::{Texture, Flip, TextureSettings};
 use piston::{Button, ButtonArgs, ButtonState, Input, Loop, Motion, MouseButton, Events};
 use piston::window::WindowSettings;
 use piston_window::{Event, PistonWindow, G2dTexture};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 use app::App;
 use constants::{RENDERER, EVENT_SETTINGS};
+use database::DatabaseOptions;
+use opening_book::BookOptions;
+use options::Options;
+use player::Player;
+use puzzle_generator::GeneratorOptions;
 
 mod constants;
 mod player;
@@ -17,12 +22,92 @@ mod game;
 mod app;
 mod animation;
 mod gravity_floor_state;
+mod clock;
+mod game_match;
+mod screen;
+mod ai;
+mod participant;
+mod start_policy;
+mod options;
+mod profile;
+mod rating;
+mod game_move;
+mod variant;
+mod pop_state;
+mod piece;
+mod bomb_state;
+mod seat;
+mod scale_in_state;
+mod rules;
+mod position;
+mod puzzle;
+mod puzzle_generator;
+mod opening_book;
+mod zobrist;
+mod database;
+mod explorer;
+mod editor;
+mod solver;
 
 type Pos = (f64, f64);
 type Size = (f64, f64);
 
 fn main() {
-    let mut window: PistonWindow = WindowSettings::new("Four Wins", [800, 800])
+    let assets = find_folder::Search::ParentsThenKids(3, 3)
+        .for_folder("assets")
+        .unwrap_or_else(|e| panic!("Failed to find asset folder: {}", e));
+
+    rules::script::load(&assets.join("variants"));
+    opening_book::load(&assets.join(opening_book::BOOK_FILE));
+
+    let mut args = std::env::args().skip(1).peekable();
+
+    // Tools run on the terminal without opening the window
+    if args.peek().is_some_and(|arg| arg == "generate-puzzles") {
+        args.next();
+
+        let options = GeneratorOptions::from_args(args)
+            .unwrap_or_else(|e| panic!("Invalid arguments: {}", e));
+
+        puzzle_generator::run(&options);
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "import-games" || arg == "query-games") {
+        let import = args.next().is_some_and(|arg| arg == "import-games");
+
+        let options = DatabaseOptions::from_args(args)
+            .unwrap_or_else(|e| panic!("Invalid arguments: {}", e));
+
+        if import {
+            database::import(&options);
+        } else if let Err(e) = database::query(&options) {
+            eprintln!("Failed to look up the position: {}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if args.peek().is_some_and(|arg| arg == "build-book" || arg == "verify-book") {
+        let build = args.next().is_some_and(|arg| arg == "build-book");
+
+        let options = BookOptions::from_args(args, assets.join(opening_book::BOOK_FILE))
+            .unwrap_or_else(|e| panic!("Invalid arguments: {}", e));
+
+        if build {
+            opening_book::build(&options);
+        } else if !opening_book::verify(&options) {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    let options = Options::from_args(args)
+        .unwrap_or_else(|e| panic!("Invalid arguments: {}", e));
+
+    let mut window: PistonWindow = WindowSettings::new("Four Wins", [1000, 800])
         .graphics_api(RENDERER)
         .samples(2)
         .resizable(false)
@@ -32,10 +117,6 @@ fn main() {
     
     let mut events = Events::new(EVENT_SETTINGS);
 
-    let assets = find_folder::Search::ParentsThenKids(3, 3)
-        .for_folder("assets")
-        .unwrap_or_else(|e| panic!("Failed to find asset folder: {}", e));
-
     let glyphs = window.load_font(assets.join("RobotoMono-Regular.ttf")).unwrap();
     
     let tile = assets.join("tile.png");
@@ -56,43 +137,37 @@ fn main() {
             &TextureSettings::new()
         ).unwrap();
 
-    let coin_yellow = assets.join("coin-yellow.png");
+    let mut coins = HashMap::new();
+    let mut arrows = HashMap::new();
 
-    let coin_yellow: G2dTexture = Texture::from_path(
-            &mut window.create_texture_context(),
-            &coin_yellow,
-            Flip::None,
-            &TextureSettings::new()
-        ).unwrap();
-    
-    let coin_red = assets.join("coin-red.png");
+    for player in Player::ALL {
+        let color = player.text().to_lowercase();
 
-    let coin_red: G2dTexture = Texture::from_path(
-            &mut window.create_texture_context(),
-            &coin_red,
-            Flip::None,
-            &TextureSettings::new()
-        ).unwrap();
+        let coin = assets.join(format!("coin-{color}.png"));
 
-    let arrow_yellow = assets.join("arrow-yellow.png");
+        let coin: G2dTexture = Texture::from_path(
+                &mut window.create_texture_context(),
+                &coin,
+                Flip::None,
+                &TextureSettings::new()
+            ).unwrap();
 
-    let arrow_yellow: G2dTexture = Texture::from_path(
-            &mut window.create_texture_context(),
-            &arrow_yellow,
-            Flip::None,
-            &TextureSettings::new()
-        ).unwrap();
-    
-    let arrow_red = assets.join("arrow-red.png");
+        let arrow = assets.join(format!("arrow-{color}.png"));
 
-    let arrow_red: G2dTexture = Texture::from_path(
-            &mut window.create_texture_context(),
-            &arrow_red,
-            Flip::None,
-            &TextureSettings::new()
-        ).unwrap();
+        let arrow: G2dTexture = Texture::from_path(
+                &mut window.create_texture_context(),
+                &arrow,
+                Flip::None,
+                &TextureSettings::new()
+            ).unwrap();
 
-    let mut app = App::initial(glyphs, tile, tile_bg, coin_yellow, coin_red, arrow_yellow, arrow_red);
+        coins.insert(player, coin);
+        arrows.insert(player, arrow);
+    }
+
+    let mut app = App::initial(glyphs, tile, tile_bg, coins, arrows)
+        .with_options(options)
+        .with_puzzles(puzzle::load(&assets.join("puzzles.txt")));
     
     let mut frames: VecDeque<f64> = VecDeque::with_capacity(10);
 
@@ -137,6 +212,26 @@ fn main() {
                 app.handle_click();
             }
 
+            Event::Input(
+                Input::Button(
+                    ButtonArgs {
+                        state: ButtonState::Press,
+                        button: Button::Mouse(MouseButton::Right),
+                        ..
+                    }), _) => {
+                app.handle_right_click();
+            }
+
+            Event::Input(
+                Input::Button(
+                    ButtonArgs {
+                        state: ButtonState::Press,
+                        button: Button::Keyboard(key),
+                        ..
+                    }), _) => {
+                app.handle_key(key);
+            }
+
             Event::Input(Input::Move(Motion::MouseCursor([x, y])), _) => {
                 app.set_mouse_pos((x, y));
             }
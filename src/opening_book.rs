@@ -0,0 +1,335 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::game_state::GameState;
+use crate::player::Player;
+use crate::position;
+use crate::seat::Seat;
+use crate::solver::{Solver, Value};
+use crate::variant::Variant;
+
+pub const BOOK_FILE: &str = "book.txt";
+
+static BOOK: OnceLock<OpeningBook> = OnceLock::new();
+
+/// The move perfect play picks and what it leads to, stored for the canonical side of the position
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct BookEntry {
+    pub mv: Move,
+    /// The value of the position for the player to move
+    pub value: Value,
+}
+
+/// Solved moves for the first moves of a game, so the AI doesn't have to search
+/// them. A position and its mirror image share one entry, written down under
+/// whichever of the two texts sorts first.
+#[derive(Default)]
+pub struct OpeningBook {
+    /// By variant name and canonical key, the move is for the side with the lower key
+    entries: HashMap<(String, u64), BookEntry>,
+}
+
+impl OpeningBook {
+    /// Tab separated: canonical position, player to move, move, value and variant, e.g.
+    /// `......./......./......./......./......./.......\tYellow\t4\twin 21\tClassic`
+    fn parse_line(line: &str) -> Result<(Variant, String, Player, BookEntry), String> {
+        let [position, player, mv, value, variant] = line.split('\t').collect::<Vec<&str>>()[..] else {
+            return Err(String::from("Expected 5 fields"));
+        };
+
+        let [mv] = Move::parse_list(mv)?[..] else {
+            return Err(format!("Expected one move, found '{mv}'"));
+        };
+
+        let entry = BookEntry { mv, value: Value::parse(value)? };
+
+        Ok((Variant::parse(variant)?, String::from(position), Player::parse(player)?, entry))
+    }
+
+    fn to_line(variant: &str, position: &str, player: Player, entry: &BookEntry) -> String {
+        format!("{}\t{}\t{}\t{}\t{}", position, player.text(), Move::list_text(&[entry.mv]), entry.value.text(), variant)
+    }
+
+    /// The entry for the position, with the move turned around if the game is the mirror image
+    pub fn lookup(&self, game: &Game) -> Option<BookEntry> {
+        let GameState::Running(_) = game.state else { return None; };
+
+        // Free-for-all and boards with more to them than the coins aren't in the book
+        if game.active_players().len() != 2 || !game.gravity || !game.rules().board_is_position() {
+            return None;
+        }
+
+        let mut entry = *self.entries.get(&(String::from(game.variant.text()), game.canonical_key()))?;
+
+        if game.mirror_key() < game.key() {
+            entry.mv = entry.mv.mirrored(game.columns());
+        }
+
+        Some(entry)
+    }
+
+    /// Adds the entry of a book line
+    fn insert(&mut self, line: &str) -> Result<(), String> {
+        let (variant, position, player, mut entry) = OpeningBook::parse_line(line)?;
+        let game = position::game(variant, &position, player)?;
+
+        if game.mirror_key() < game.key() {
+            entry.mv = entry.mv.mirrored(game.columns());
+        }
+
+        self.entries.insert((String::from(variant.text()), game.canonical_key()), entry);
+
+        Ok(())
+    }
+
+    /// The book move for the position, if there is one and it is legal
+    pub fn best_move(&self, game: &Game) -> Option<Move> {
+        self.lookup(game)
+            .map(|entry| entry.mv)
+            .filter(|mv| game.legal_moves().contains(mv))
+    }
+}
+
+/// Reads the opening book, broken lines are reported and left out
+pub fn load(path: &Path) {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read opening book: {}", e);
+            return;
+        }
+    };
+
+    let mut book = OpeningBook::default();
+
+    for (i, line) in content.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Err(e) = book.insert(line) {
+            eprintln!("Skipping book entry {} in {}: {}", i + 1, path.display(), e);
+        }
+    }
+
+    if BOOK.set(book).is_err() {
+        eprintln!("The opening book is already loaded");
+    }
+}
+
+/// The loaded book, empty if there is none
+pub fn book() -> &'static OpeningBook {
+    BOOK.get_or_init(OpeningBook::default)
+}
+
+pub struct BookOptions {
+    /// How many moves into the game the book reaches
+    pub plies: usize,
+    pub variant: Variant,
+    pub path: PathBuf,
+}
+
+impl BookOptions {
+    /// Parses `--plies <count>`, `--variant <name>` and `--book <path>`, the book is `path` unless given
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I, path: PathBuf) -> Result<BookOptions, String> {
+        let mut options = BookOptions { plies: 4, variant: Variant::Classic, path };
+
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or(format!("Missing value for {arg}"))?;
+
+            match arg.as_str() {
+                "--plies" => options.plies = value.parse().map_err(|_| format!("Invalid count '{value}' for {arg}"))?,
+                "--variant" => options.variant = Variant::parse(&value)?,
+                "--book" => options.path = PathBuf::from(value),
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        if !options.variant.rules().solvable() {
+            return Err(format!("{} can't be solved for a book", options.variant.text()));
+        }
+
+        Ok(options)
+    }
+}
+
+/// Solves every position of the first moves, from either player starting,
+/// and writes the book. Entries of other variants already in the book are kept.
+pub fn build(options: &BookOptions) {
+    let variant = options.variant.text();
+    let mut lines: Vec<String> = fs::read_to_string(&options.path).unwrap_or_default()
+        .lines()
+        .filter(|line| !line.starts_with('#') && OpeningBook::parse_line(line).is_ok_and(|(other, ..)| other.text() != variant))
+        .map(String::from)
+        .collect();
+
+    let players = [Player::Yellow, Player::Red];
+    let mut seen = HashSet::new();
+    let mut positions = vec![];
+
+    let mut level: Vec<Game> = players.iter()
+        .map(|&player| {
+            let mut game = Game::initial(options.variant, Seat::solo(&players));
+            game.start(player);
+            game
+        })
+        .collect();
+
+    for _ in 0..options.plies {
+        let mut next = vec![];
+
+        for game in level {
+            let GameState::Running(player) = game.state else { continue; };
+
+            if !seen.insert(game.canonical_key()) {
+                continue;
+            }
+
+            for mv in game.legal_moves() {
+                let mut child = game.clone();
+                child.apply(mv);
+                next.push(child);
+            }
+
+            positions.push((position::canonical(&game.board).0, player));
+        }
+
+        level = next;
+    }
+
+    let Some(mut solver) = Solver::new(&Game::initial(options.variant, Seat::solo(&players))) else {
+        eprintln!("Failed to build opening book: {} can't be solved", variant);
+        return;
+    };
+
+    // The deepest positions first, what the solver learns about them is kept for the shallower ones
+    for (i, (position, player)) in positions.iter().enumerate().rev() {
+        let Ok(game) = position::game(options.variant, position, *player) else { continue; };
+        let Some((mv, value)) = solver.solve(&game) else { continue; };
+
+        lines.push(OpeningBook::to_line(variant, position, *player, &BookEntry { mv, value }));
+        eprintln!("{} positions left", i);
+    }
+
+    lines.sort();
+
+    let content = format!(
+        "# Position, player to move, move, value and variant, one entry per position and its mirror image\n{}\n",
+        lines.join("\n"),
+    );
+
+    if let Err(e) = fs::write(&options.path, content) {
+        eprintln!("Failed to write opening book: {}", e);
+    }
+}
+
+/// Checks every entry of the book against the rules and solves it again, reports
+/// the broken ones and whether all of them are fine
+pub fn verify(options: &BookOptions) -> bool {
+    let content = match fs::read_to_string(&options.path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read opening book: {}", e);
+            return false;
+        }
+    };
+
+    let mut seen = HashSet::new();
+    let mut checked = vec![];
+    let mut entries = 0;
+    let mut problems = 0;
+
+    for (i, line) in content.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        entries += 1;
+
+        match verify_line(line, &mut seen) {
+            Ok((game, entry)) => checked.push((i, game, entry)),
+            Err(e) => {
+                eprintln!("Line {}: {}", i + 1, e);
+                problems += 1;
+            }
+        }
+    }
+
+    // Fullest boards first, like the book was built
+    checked.sort_by_key(|(_, game, _)| Reverse(game.board.iter().flatten().flatten().count()));
+
+    let mut solvers: HashMap<String, Solver> = HashMap::new();
+
+    for (i, game, entry) in checked {
+        let variant = String::from(game.variant.text());
+
+        if !solvers.contains_key(&variant) {
+            let Some(solver) = Solver::new(&game) else { continue; };
+            solvers.insert(variant.clone(), solver);
+        }
+
+        if let Err(e) = verify_value(solvers.get_mut(&variant).unwrap(), &game, &entry) {
+            eprintln!("Line {}: {}", i + 1, e);
+            problems += 1;
+        }
+    }
+
+    println!("{} entries, {} problems", entries, problems);
+
+    problems == 0
+}
+
+fn verify_line(line: &str, seen: &mut HashSet<(String, u64)>) -> Result<(Game, BookEntry), String> {
+    let (variant, position, player, entry) = OpeningBook::parse_line(line)?;
+
+    if !variant.rules().solvable() {
+        return Err(format!("{} can't be solved for a book", variant.text()));
+    }
+
+    let game = position::game(variant, &position, player)?;
+
+    if !game.rules().winners(&game).0.is_empty() {
+        return Err(String::from("The position is already decided"));
+    }
+
+    if position::canonical(&game.board).0 != position {
+        return Err(String::from("The position is stored as its mirror image"));
+    }
+
+    if !game.legal_moves().contains(&entry.mv) {
+        return Err(format!("Illegal move {}", Move::list_text(&[entry.mv])));
+    }
+
+    if !seen.insert((String::from(variant.text()), game.canonical_key())) {
+        return Err(String::from("The position is in the book twice"));
+    }
+
+    Ok((game, entry))
+}
+
+/// Whether the position solves to the entry's value and its move keeps it
+fn verify_value(solver: &mut Solver, game: &Game, entry: &BookEntry) -> Result<(), String> {
+    let Some((_, value)) = solver.solve(game) else {
+        return Err(String::from("The position can't be solved"));
+    };
+
+    if value != entry.value {
+        return Err(format!("The value is {}, the book says {}", value.text(), entry.value.text()));
+    }
+
+    let mv_value = solver.move_value(game, entry.mv);
+
+    if mv_value != Some(value) {
+        let text = mv_value.map_or(String::from("nothing"), |value| value.text());
+        return Err(format!("Move {} leads to {}, not {}", Move::list_text(&[entry.mv]), text, value.text()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use crate::participant::Participant;
+use crate::start_policy::StartPolicy;
+
+pub struct Options {
+    pub yellow: Participant,
+    pub red: Participant,
+    /// Green and blue only play in games of three or four
+    pub green: Option<Participant>,
+    pub blue: Option<Participant>,
+    /// Second members of the yellow and red teams in team play
+    pub yellow_partner: Option<Participant>,
+    pub red_partner: Option<Participant>,
+    pub start_policy: StartPolicy,
+    pub data_dir: PathBuf,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            yellow: Participant::human("Yellow"),
+            red: Participant::human("Red"),
+            green: None,
+            blue: None,
+            yellow_partner: None,
+            red_partner: None,
+            start_policy: StartPolicy::Alternate,
+            data_dir: dirs::data_dir().unwrap_or_default().join("forza-quattro"),
+        }
+    }
+}
+
+impl Options {
+    /// Parses `--yellow <participant>`, `--red <participant>`, `--green <participant>`,
+    /// `--blue <participant>`, `--yellow-partner <participant>`, `--red-partner <participant>`,
+    /// `--start <policy>` and `--data-dir <path>`
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or(format!("Missing value for {arg}"))?;
+
+            match arg.as_str() {
+                "--yellow" => options.yellow = Participant::parse(&value)?,
+                "--red" => options.red = Participant::parse(&value)?,
+                "--green" => options.green = Some(Participant::parse(&value)?),
+                "--blue" => options.blue = Some(Participant::parse(&value)?),
+                "--yellow-partner" => options.yellow_partner = Some(Participant::parse(&value)?),
+                "--red-partner" => options.red_partner = Some(Participant::parse(&value)?),
+                "--start" => options.start_policy = StartPolicy::parse(&value)?,
+                "--data-dir" => options.data_dir = PathBuf::from(value),
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        let partners = (options.yellow_partner.is_some(), options.red_partner.is_some());
+        let more_colors = options.green.is_some() || options.blue.is_some();
+
+        match (partners, more_colors) {
+            ((false, false), _) | ((true, true), false) => Ok(options),
+            ((true, true), true) => Err(String::from("Team play is only for yellow and red")),
+            _ => Err(String::from("Team play needs partners for both yellow and red")),
+        }
+    }
+}
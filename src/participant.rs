@@ -0,0 +1,43 @@
+use crate::ai::Difficulty;
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum ParticipantKind {
+    Human,
+    Ai(Difficulty),
+}
+
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Participant {
+    pub name: String,
+    pub kind: ParticipantKind,
+}
+
+impl Participant {
+    pub fn human(name: &str) -> Self {
+        Self { name: String::from(name), kind: ParticipantKind::Human }
+    }
+
+    pub fn ai(difficulty: Difficulty) -> Self {
+        Self { name: format!("AI ({})", difficulty.text()), kind: ParticipantKind::Ai(difficulty) }
+    }
+
+    /// Parses `ai`, `ai:<difficulty>` or the name of a human
+    pub fn parse(text: &str) -> Result<Participant, String> {
+        match text.split_once(':') {
+            Some(("ai", difficulty)) => Ok(Self::ai(Difficulty::parse(difficulty)?)),
+            _ if text == "ai" => Ok(Self::ai(Difficulty::Medium)),
+            _ if text.trim().is_empty() => Err(String::from("Participant names must not be empty")),
+            _ => Ok(Self::human(text)),
+        }
+    }
+
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        match self.kind {
+            ParticipantKind::Human => None,
+            ParticipantKind::Ai(difficulty) => Some(difficulty),
+        }
+    }
+}
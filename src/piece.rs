@@ -0,0 +1,67 @@
+use crate::player::Player;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
+pub enum PieceKind {
+    Normal,
+    /// Crushes every coin beneath it and lands at the bottom of the column
+    Anvil,
+    /// Takes up a cell but never counts toward a line
+    Wall,
+    /// Blows up together with the opponent coin it lands on
+    Bomb,
+    /// Its player moves again right away
+    Double,
+}
+
+impl PieceKind {
+    pub const POWERS: [PieceKind; 4] = [PieceKind::Anvil, PieceKind::Wall, PieceKind::Bomb, PieceKind::Double];
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            PieceKind::Normal => "coin",
+            PieceKind::Anvil => "anvil",
+            PieceKind::Wall => "wall",
+            PieceKind::Bomb => "bomb",
+            PieceKind::Double => "x2",
+        }
+    }
+
+    /// Prefix of a power coin's move in a move list
+    pub fn letter(&self) -> char {
+        match self {
+            PieceKind::Normal => 'n',
+            PieceKind::Anvil => 'a',
+            PieceKind::Wall => 'w',
+            PieceKind::Bomb => 'b',
+            PieceKind::Double => 'x',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<PieceKind> {
+        Self::POWERS.into_iter().find(|kind| kind.letter() == letter)
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct Piece {
+    pub player: Player,
+    pub kind: PieceKind,
+}
+
+impl Piece {
+    pub fn new(player: Player, kind: PieceKind) -> Piece {
+        Self { player, kind }
+    }
+
+    pub fn coin(player: Player) -> Piece {
+        Self::new(player, PieceKind::Normal)
+    }
+}
@@ -2,32 +2,64 @@ use graphics::types::Color;
 
 #[derive(Clone)]
 #[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(PartialOrd)]
+#[derive(Ord)]
 #[derive(Debug)]
 #[derive(Copy)]
 pub enum Player {
     Yellow,
     Red,
+    Green,
+    Blue,
 }
 
 impl Player {
+    /// Seat order, yellow and red always play
+    pub const ALL: [Player; 4] = [Player::Yellow, Player::Red, Player::Green, Player::Blue];
+
+    /// The opponent in a game of two, games of more players rotate with `next_in`
     pub fn op(&self) -> Player {
         match self {
             Player::Yellow => Player::Red,
-            Player::Red => Player::Yellow
+            Player::Red => Player::Yellow,
+            Player::Green => Player::Blue,
+            Player::Blue => Player::Green,
         }
     }
 
+    /// The player seated after this one
+    pub fn next_in(&self, players: &[Player]) -> Player {
+        let i = players.iter().position(|player| player == self).unwrap_or(0);
+        players[(i + 1) % players.len()]
+    }
+
     pub fn color(&self) -> Color {
         match self {
             Player::Yellow => graphics::color::YELLOW,
-            Player::Red => graphics::color::RED
+            Player::Red => graphics::color::RED,
+            Player::Green => graphics::color::GREEN,
+            Player::Blue => graphics::color::BLUE,
         }
     }
 
-    pub fn text(&self) -> &str {
+    pub fn text(&self) -> &'static str {
         match self {
             Player::Yellow => "Yellow",
             Player::Red => "Red",
+            Player::Green => "Green",
+            Player::Blue => "Blue",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Player, String> {
+        match text {
+            "Yellow" => Ok(Player::Yellow),
+            "Red" => Ok(Player::Red),
+            "Green" => Ok(Player::Green),
+            "Blue" => Ok(Player::Blue),
+            _ => Err(format!("Unknown player '{text}'")),
         }
     }
 }
@@ -0,0 +1,31 @@
+use crate::animation::AnimationStatus;
+use crate::animation::animation_state::AnimationState;
+use crate::constants::GRAVITY;
+
+/// Lifts a popped coin out of the board, speeding up until it reaches `ceiling`
+#[derive(Debug)]
+pub struct PopState {
+    pub position: (f64, f64),
+    velocity: (f64, f64),
+    ceiling: f64,
+}
+
+impl PopState {
+    pub fn new(position: (f64, f64), velocity: (f64, f64), ceiling: f64) -> Self {
+        Self { position, velocity, ceiling }
+    }
+}
+
+impl AnimationState for PopState {
+    fn update(&mut self, dt: f64) -> AnimationStatus {
+        self.velocity = (self.velocity.0, self.velocity.1 - GRAVITY * dt);
+        self.position = (self.position.0, self.position.1 + self.velocity.1 * dt);
+
+        if self.position.1 <= self.ceiling {
+            self.position.1 = self.ceiling;
+            return AnimationStatus::Finished;
+        }
+
+        AnimationStatus::Running
+    }
+}
@@ -0,0 +1,90 @@
+use crate::game::Game;
+use crate::piece::Piece;
+use crate::player::Player;
+use crate::seat::Seat;
+use crate::variant::Variant;
+
+/// Reads a board written as its rows from top to bottom separated by `/`, empty
+/// cells as `.` and coins as the initial of their color, e.g. `.../.../YR.`.
+/// With gravity no coin may float above an empty cell.
+pub fn parse(text: &str, gravity: bool) -> Result<Vec<Vec<Option<Piece>>>, String> {
+    let rows: Vec<&str> = text.split('/').rev().collect();
+    let columns = rows[0].chars().count();
+
+    if columns == 0 || rows.iter().any(|row| row.chars().count() != columns) {
+        return Err(format!("Rows of different lengths in '{text}'"));
+    }
+
+    let mut board = vec![vec![None; rows.len()]; columns];
+
+    for (row, line) in rows.iter().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            board[col][row] = match c {
+                '.' => None,
+                c => Some(Piece::coin(Player::ALL.into_iter()
+                    .find(|&player| letter(player) == c)
+                    .ok_or(format!("Invalid cell '{c}'"))?)),
+            };
+        }
+    }
+
+    if let Some(col) = floating_column(&board).filter(|_| gravity) {
+        return Err(format!("Coin floating in column {}", col + 1));
+    }
+
+    Ok(board)
+}
+
+/// The first column with a coin above an empty cell, which gravity doesn't allow
+pub fn floating_column(board: &[Vec<Option<Piece>>]) -> Option<usize> {
+    board.iter().position(|column| column.windows(2).any(|cells| cells[0].is_none() && cells[1].is_some()))
+}
+
+/// A two player game at the position with gravity, `player` to move against their opponent
+pub fn game(variant: Variant, text: &str, player: Player) -> Result<Game, String> {
+    let players = [player, player.op()];
+    let mut seats = Seat::solo(&players);
+    seats.sort();
+
+    let mut game = Game::initial(variant, seats);
+    let board = parse(text, true)?;
+
+    if (board.len(), board[0].len()) != (game.columns(), game.rows()) {
+        return Err(format!("Expected a {}x{} board", game.columns(), game.rows()));
+    }
+
+    game.set_board(board);
+    game.start(player);
+
+    Ok(game)
+}
+
+/// Writes the board the way `parse` reads it
+pub fn text(board: &[Vec<Option<Piece>>]) -> String {
+    let rows = board.first().map_or(0, |column| column.len());
+
+    (0..rows).rev()
+        .map(|row| board.iter()
+            .map(|column| column[row].map_or('.', |piece| letter(piece.player)))
+            .collect::<String>())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// The board seen in a mirror, columns from right to left
+pub fn mirrored(board: &[Vec<Option<Piece>>]) -> Vec<Vec<Option<Piece>>> {
+    board.iter().rev().cloned().collect()
+}
+
+/// The text of the board or of its mirror image, whichever sorts first, so
+/// both read the same. Also tells whether the mirror image was taken.
+pub fn canonical(board: &[Vec<Option<Piece>>]) -> (String, bool) {
+    let text = text(board);
+    let mirrored = self::text(&mirrored(board));
+
+    if mirrored < text { (mirrored, true) } else { (text, false) }
+}
+
+fn letter(player: Player) -> char {
+    player.text().chars().next().unwrap_or('?')
+}
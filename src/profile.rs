@@ -0,0 +1,279 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::game_move::Move;
+use crate::player::Player;
+use crate::variant::Variant;
+
+const RECORDS_FILE: &str = "games.txt";
+const SOLVED_FILE: &str = "solved.txt";
+
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct GameRecord {
+    pub yellow: String,
+    pub red: String,
+    pub first: Player,
+    pub winner: Option<Player>,
+    pub moves: Vec<Move>,
+    pub variant: Variant,
+}
+
+impl GameRecord {
+    pub fn name(&self, player: Player) -> &str {
+        match player {
+            Player::Yellow => &self.yellow,
+            Player::Red => &self.red,
+            // Only games of two players are recorded
+            Player::Green | Player::Blue => "",
+        }
+    }
+
+    /// How the game went for the player
+    pub fn outcome(&self, player: Player) -> Outcome {
+        match self.winner {
+            Some(winner) if winner == player => Outcome::Win,
+            Some(_) => Outcome::Loss,
+            None => Outcome::Draw,
+        }
+    }
+
+    /// Tab separated: yellow, red, first player, winner, moves and variant
+    pub fn to_line(&self) -> String {
+        let winner = self.winner.map_or("draw", |player| player.text());
+
+        format!("{}\t{}\t{}\t{}\t{}\t{}",
+                self.yellow.replace('\t', " "),
+                self.red.replace('\t', " "),
+                self.first.text(),
+                winner,
+                Move::list_text(&self.moves),
+                self.variant.text())
+    }
+
+    pub fn parse(line: &str) -> Result<GameRecord, String> {
+        let fields: Vec<&str> = line.split('\t').collect();
+
+        // Records from before variants existed are classic games
+        let [yellow, red, first, winner, moves, variant] = match fields[..] {
+            [yellow, red, first, winner, moves] => [yellow, red, first, winner, moves, Variant::Classic.text()],
+            [yellow, red, first, winner, moves, variant] => [yellow, red, first, winner, moves, variant],
+            _ => return Err(format!("Expected 6 fields, found {}", fields.len())),
+        };
+
+        let winner = match winner {
+            "draw" => None,
+            winner => Some(Player::parse(winner)?),
+        };
+
+        Ok(GameRecord {
+            yellow: String::from(yellow),
+            red: String::from(red),
+            first: Player::parse(first)?,
+            winner,
+            moves: Move::parse_list(moves)?,
+            variant: Variant::parse(variant)?,
+        })
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Outcome {
+    pub fn text(&self) -> &'static str {
+        match self {
+            Outcome::Win => "W",
+            Outcome::Loss => "L",
+            Outcome::Draw => "D",
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Default)]
+#[derive(Debug)]
+pub struct Tally {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Tally {
+    pub fn add(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Win => self.wins += 1,
+            Outcome::Loss => self.losses += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    pub fn text(&self) -> String {
+        format!("{}W {}L {}D", self.wins, self.losses, self.draws)
+    }
+}
+
+#[derive(Default)]
+#[derive(Debug)]
+pub struct Stats {
+    pub total: Tally,
+    pub by_color: HashMap<Player, Tally>,
+    pub by_opponent: BTreeMap<String, Tally>,
+    pub total_moves: usize,
+    pub streak: Option<(Outcome, u32)>,
+    pub best_win_streak: u32,
+}
+
+impl Stats {
+    fn add(&mut self, record: &GameRecord, player: Player) {
+        let outcome = record.outcome(player);
+
+        self.total.add(outcome);
+        self.by_color.entry(player).or_default().add(outcome);
+        self.by_opponent.entry(String::from(record.name(player.op()))).or_default().add(outcome);
+        self.total_moves += record.moves.len();
+
+        self.streak = match self.streak {
+            Some((last, count)) if last == outcome => Some((outcome, count + 1)),
+            _ => Some((outcome, 1)),
+        };
+
+        if let Some((Outcome::Win, count)) = self.streak {
+            self.best_win_streak = self.best_win_streak.max(count);
+        }
+    }
+
+    pub fn average_length(&self) -> f64 {
+        match self.total.games() {
+            0 => 0.0,
+            games => self.total_moves as f64 / games as f64,
+        }
+    }
+}
+
+/// Keeps the record of every finished game, the statistics of a profile are
+/// computed from them. The default store is not saved anywhere.
+#[derive(Default)]
+pub struct ProfileStore {
+    dir: Option<PathBuf>,
+    records: Vec<GameRecord>,
+    /// The positions of the puzzles each profile has solved
+    solved: BTreeSet<(String, String)>,
+}
+
+impl ProfileStore {
+    pub fn open(dir: &Path) -> ProfileStore {
+        let mut records = vec![];
+
+        match fs::read_to_string(dir.join(RECORDS_FILE)) {
+            Ok(content) => {
+                for (i, line) in content.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+                    match GameRecord::parse(line) {
+                        Ok(record) => records.push(record),
+                        Err(e) => eprintln!("Skipping game record {} in {}: {}", i + 1, RECORDS_FILE, e),
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => eprintln!("Failed to read game records: {}", e),
+        }
+
+        let mut solved = BTreeSet::new();
+
+        match fs::read_to_string(dir.join(SOLVED_FILE)) {
+            Ok(content) => {
+                for (i, line) in content.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+                    match line.split_once('\t') {
+                        Some((name, position)) => { solved.insert((String::from(name), String::from(position))); }
+                        None => eprintln!("Skipping solved puzzle {} in {}: Expected 2 fields", i + 1, SOLVED_FILE),
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => eprintln!("Failed to read solved puzzles: {}", e),
+        }
+
+        Self { dir: Some(dir.to_path_buf()), records, solved }
+    }
+
+    pub fn add(&mut self, record: GameRecord) -> io::Result<()> {
+        let line = record.to_line();
+        self.records.push(record);
+
+        self.append(RECORDS_FILE, &line)
+    }
+
+    /// Marks the puzzle with this position as solved by the profile
+    pub fn solve(&mut self, name: &str, position: &str) -> io::Result<()> {
+        let name = name.replace('\t', " ");
+
+        if !self.solved.insert((name.clone(), String::from(position))) {
+            return Ok(());
+        }
+
+        self.append(SOLVED_FILE, &format!("{name}\t{position}"))
+    }
+
+    pub fn is_solved(&self, name: &str, position: &str) -> bool {
+        self.solved.contains(&(String::from(name), String::from(position)))
+    }
+
+    /// How many puzzles the profile has solved
+    pub fn solved(&self, name: &str) -> usize {
+        self.solved.iter().filter(|(solver, _)| solver == name).count()
+    }
+
+    fn append(&self, file_name: &str, line: &str) -> io::Result<()> {
+        let Some(dir) = &self.dir else { return Ok(()); };
+
+        fs::create_dir_all(dir)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(file_name))?;
+
+        writeln!(file, "{line}")
+    }
+
+    pub fn records(&self) -> &[GameRecord] {
+        &self.records
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let names: BTreeSet<&String> = self.records.iter()
+            .flat_map(|record| [&record.yellow, &record.red])
+            .chain(self.solved.iter().map(|(name, _)| name))
+            .collect();
+
+        names.into_iter().cloned().collect()
+    }
+
+    pub fn stats(&self, name: &str) -> Stats {
+        let mut stats = Stats::default();
+
+        for record in &self.records {
+            for player in [Player::Yellow, Player::Red] {
+                if record.name(player) == name {
+                    stats.add(record, player);
+                }
+            }
+        }
+
+        stats
+    }
+}
@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::Path;
+
+use crate::game::Game;
+use crate::game_state::GameState;
+use crate::player::Player;
+use crate::position;
+use crate::variant::Variant;
+
+/// A position where one player is to move and can force a win
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Puzzle {
+    pub position: String,
+    pub player: Player,
+    /// The player has to win with at most this many of their own moves
+    pub moves: u32,
+    pub variant: Variant,
+}
+
+impl Puzzle {
+    /// Tab separated: position, goal and optionally the variant, e.g.
+    /// `.../YR.\tYellow to play and win in 2\tClassic`
+    pub fn parse(line: &str) -> Result<Puzzle, String> {
+        let fields: Vec<&str> = line.split('\t').collect();
+
+        let [position, goal, variant] = match fields[..] {
+            [position, goal] => [position, goal, Variant::Classic.text()],
+            [position, goal, variant] => [position, goal, variant],
+            _ => return Err(format!("Expected 2 or 3 fields, found {}", fields.len())),
+        };
+
+        let (player, moves) = match goal.split(' ').collect::<Vec<&str>>()[..] {
+            [player, "to", "play", "and", "win", "in", moves] => (
+                Player::parse(player)?,
+                moves.parse::<u32>().map_err(|_| format!("Invalid number of moves '{moves}'"))?,
+            ),
+            _ => return Err(format!("Invalid goal '{goal}'")),
+        };
+
+        let variant = Variant::parse(variant)?;
+
+        // Captures, power coins and rotations left aren't part of the position
+        if !variant.rules().board_is_position() {
+            return Err(format!("{} can't be written as a puzzle", variant.text()));
+        }
+
+        let puzzle = Puzzle {
+            position: String::from(position),
+            player,
+            moves,
+            variant,
+        };
+
+        let game = puzzle.game()?;
+
+        if !game.rules().winners(&game).0.is_empty() {
+            return Err(String::from("The position is already decided"));
+        }
+
+        Ok(puzzle)
+    }
+
+    /// The line `parse` reads the puzzle from
+    pub fn to_line(&self) -> String {
+        format!("{}\t{}\t{}", self.position, self.goal(), self.variant.text())
+    }
+
+    /// E.g. "Yellow to play and win in 3"
+    pub fn goal(&self) -> String {
+        format!("{} to play and win in {}", self.player.text(), self.moves)
+    }
+
+    /// The position with the puzzle's player to move, against their opponent
+    pub fn game(&self) -> Result<Game, String> {
+        position::game(self.variant, &self.position, self.player)
+    }
+
+    /// Whether the game so far solves the puzzle, `None` while it is still open
+    pub fn solved(&self, game: &Game) -> Option<bool> {
+        // The player moves first, so their n-th move is move 2n - 1
+        let player_moves = (game.moves.len() as u32).div_ceil(2);
+
+        match game.state {
+            GameState::Win { player, .. } => Some(player == self.player && player_moves <= self.moves),
+            GameState::Draw(_) => Some(false),
+            GameState::Running(_) if player_moves >= self.moves => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the puzzles of a puzzle file, broken lines are reported and left out
+pub fn load(path: &Path) -> Vec<Puzzle> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read puzzles: {}", e);
+            return vec![];
+        }
+    };
+
+    content.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|(i, line)| Puzzle::parse(line)
+            .map_err(|e| eprintln!("Skipping puzzle {} in {}: {}", i + 1, path.display(), e))
+            .ok())
+        .collect()
+}
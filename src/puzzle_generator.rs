@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::ai::{self, Difficulty};
+use crate::game::Game;
+use crate::game_state::GameState;
+use crate::player::Player;
+use crate::position;
+use crate::puzzle::Puzzle;
+use crate::seat::Seat;
+use crate::variant::Variant;
+
+/// Self-play games open with up to this many random moves, the engine alone plays the same game every time
+const RANDOM_OPENING: usize = 8;
+
+pub struct GeneratorOptions {
+    pub games: usize,
+    /// The longest puzzles looked for, in moves of the winning player
+    pub moves: u32,
+    pub variant: Variant,
+    /// The engine playing both sides
+    pub difficulty: Difficulty,
+    /// Without a file the puzzles are written to the terminal
+    pub output: Option<PathBuf>,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            moves: 3,
+            variant: Variant::Classic,
+            difficulty: Difficulty::Medium,
+            output: None,
+        }
+    }
+}
+
+impl GeneratorOptions {
+    /// Parses `--games <count>`, `--moves <count>`, `--variant <name>`,
+    /// `--difficulty <difficulty>` and `--output <path>`
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<GeneratorOptions, String> {
+        let mut options = GeneratorOptions::default();
+
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or(format!("Missing value for {arg}"))?;
+            let count = || value.parse::<u32>().map_err(|_| format!("Invalid count '{value}' for {arg}"));
+
+            match arg.as_str() {
+                "--games" => options.games = count()? as usize,
+                "--moves" => options.moves = count()?.max(1),
+                "--variant" => options.variant = Variant::parse(&value)?,
+                "--difficulty" => options.difficulty = Difficulty::parse(&value)?,
+                "--output" => options.output = Some(PathBuf::from(value)),
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        if !options.variant.rules().board_is_position() {
+            return Err(format!("{} can't be written as a puzzle", options.variant.text()));
+        }
+
+        Ok(options)
+    }
+}
+
+/// A found puzzle with what makes it hard
+struct Candidate {
+    puzzle: Puzzle,
+    /// Other first moves that don't hand the opponent a win on the spot, the more the harder to spot the right one
+    decoys: usize,
+}
+
+/// Plays the self-play games, collects the puzzles where exactly one move wins
+/// and writes them from easiest to hardest
+pub fn run(options: &GeneratorOptions) {
+    let mut candidates: Vec<Candidate> = vec![];
+    let mut seen = HashSet::new();
+
+    for i in 0..options.games {
+        for game in self_play(options) {
+            let Some(candidate) = find_puzzle(&game, options) else { continue; };
+
+            // A position and its mirror image are the same puzzle
+            if seen.insert(game.canonical_key()) {
+                candidates.push(candidate);
+            }
+        }
+
+        eprintln!("Game {}/{}: {} puzzles", i + 1, options.games, candidates.len());
+    }
+
+    candidates.sort_by_key(|candidate| (candidate.puzzle.moves, candidate.decoys));
+
+    let mut content = format!("# {} puzzles from {} self-play games\n", candidates.len(), options.games);
+
+    for candidate in &candidates {
+        content.push_str(&candidate.puzzle.to_line());
+        content.push('\n');
+    }
+
+    match &options.output {
+        Some(path) => {
+            if let Err(e) = fs::write(path, content) {
+                eprintln!("Failed to write puzzles: {}", e);
+            }
+        }
+        None => print!("{content}"),
+    }
+}
+
+/// Every running position of one engine game after a random opening
+fn self_play(options: &GeneratorOptions) -> Vec<Game> {
+    let mut rng = rand::thread_rng();
+    let players = [Player::Yellow, Player::Red];
+
+    let mut game = Game::initial(options.variant, Seat::solo(&players));
+    game.start(players[rng.gen_range(0..players.len())]);
+
+    let opening = rng.gen_range(0..=RANDOM_OPENING);
+    let mut positions = vec![];
+
+    while let GameState::Running(_) = game.state {
+        positions.push(game.clone());
+
+        let mv = if game.moves.len() < opening {
+            game.legal_moves().choose(&mut rng).copied()
+        } else {
+            ai::best_move(&game, options.difficulty)
+        };
+
+        let Some(mv) = mv else { break; };
+        game.apply(mv);
+    }
+
+    positions
+}
+
+/// The puzzle of the position if its player to move wins in as few moves as
+/// possible with exactly one first move
+fn find_puzzle(game: &Game, options: &GeneratorOptions) -> Option<Candidate> {
+    let GameState::Running(player) = game.state else { return None; };
+
+    let moves = (1..=options.moves).find(|&moves| game.forced_win(player, moves))?;
+
+    // Winning in one is no puzzle
+    if moves == 1 {
+        return None;
+    }
+
+    let [key] = game.winning_moves(moves)[..] else { return None; };
+
+    let decoys = game.legal_moves().into_iter()
+        .filter(|&mv| mv != key)
+        .filter(|&mv| {
+            let mut child = game.clone();
+            child.apply(mv);
+
+            matches!(child.state, GameState::Running(_)) && child.winning_moves(1).is_empty()
+        })
+        .count();
+
+    let puzzle = Puzzle {
+        position: position::text(&game.board),
+        player,
+        moves,
+        variant: options.variant,
+    };
+
+    Some(Candidate { puzzle, decoys })
+}
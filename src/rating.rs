@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use crate::ai::Difficulty;
+use crate::participant::Participant;
+use crate::player::Player;
+use crate::profile::GameRecord;
+
+pub const INITIAL_RATING: f64 = 1200.0;
+const K_FACTOR: f64 = 32.0;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct Rating {
+    pub elo: f64,
+    pub games: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self { elo: INITIAL_RATING, games: 0 }
+    }
+}
+
+/// Replays the records in order and returns every participant's Elo rating,
+/// best first. Every AI difficulty is on the ladder, even before its first game.
+pub fn ladder(records: &[GameRecord]) -> Vec<(String, Rating)> {
+    let mut ratings: HashMap<String, Rating> = Difficulty::ALL.iter()
+        .map(|&difficulty| (Participant::ai(difficulty).name, Rating::default()))
+        .collect();
+
+    // Games against yourself don't say anything about your strength
+    for record in records.iter().filter(|record| record.yellow != record.red) {
+        let yellow = ratings.get(&record.yellow).copied().unwrap_or_default();
+        let red = ratings.get(&record.red).copied().unwrap_or_default();
+
+        let expected = 1.0 / (1.0 + 10f64.powf((red.elo - yellow.elo) / 400.0));
+        let score = match record.winner {
+            Some(Player::Yellow) => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        };
+        let change = K_FACTOR * (score - expected);
+
+        ratings.insert(record.yellow.clone(), Rating { elo: yellow.elo + change, games: yellow.games + 1 });
+        ratings.insert(record.red.clone(), Rating { elo: red.elo - change, games: red.games + 1 });
+    }
+
+    let mut ladder: Vec<(String, Rating)> = ratings.into_iter().collect();
+    ladder.sort_by(|(a_name, a), (b_name, b)| b.elo.total_cmp(&a.elo).then(a_name.cmp(b_name)));
+    ladder
+}
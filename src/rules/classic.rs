@@ -0,0 +1,14 @@
+use super::ruleset::Ruleset;
+
+/// Connect four, the default rules
+pub struct Classic;
+
+impl Ruleset for Classic {
+    fn name(&self) -> &str {
+        "Classic"
+    }
+
+    fn description(&self) -> &str {
+        "Connect four"
+    }
+}
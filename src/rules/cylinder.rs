@@ -0,0 +1,18 @@
+use super::ruleset::Ruleset;
+
+/// Horizontal and diagonal lines wrap around from the rightmost to the leftmost column
+pub struct Cylinder;
+
+impl Ruleset for Cylinder {
+    fn name(&self) -> &str {
+        "Cylinder"
+    }
+
+    fn description(&self) -> &str {
+        "Lines wrap around the edges"
+    }
+
+    fn wraps(&self) -> bool {
+        true
+    }
+}
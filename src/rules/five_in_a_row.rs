@@ -0,0 +1,40 @@
+use crate::piece::Piece;
+use crate::player::Player;
+
+use super::ruleset::Ruleset;
+
+/// Connect five on a 9x6 board whose outer columns start filled
+pub struct FiveInARow;
+
+impl Ruleset for FiveInARow {
+    fn name(&self) -> &str {
+        "Five-in-a-Row"
+    }
+
+    fn description(&self) -> &str {
+        "Connect five on a wider board"
+    }
+
+    fn columns(&self, players: usize) -> usize {
+        9 + players.saturating_sub(2)
+    }
+
+    fn initial_board(&self, players: usize) -> Vec<Vec<Option<Piece>>> {
+        let rows = self.rows(players);
+        let mut board = vec![vec![None; rows]; self.columns(players)];
+
+        let alternating = |bottom: Player| (0..rows)
+            .map(|row| Some(Piece::coin(if row % 2 == 0 { bottom } else { bottom.op() })))
+            .collect();
+
+        let last = board.len() - 1;
+        board[0] = alternating(Player::Yellow);
+        board[last] = alternating(Player::Red);
+
+        board
+    }
+
+    fn connect(&self) -> i32 {
+        5
+    }
+}
@@ -0,0 +1,183 @@
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::piece::{Piece, PieceKind};
+use crate::player::Player;
+
+pub mod ruleset;
+pub mod classic;
+pub mod pop_out;
+pub mod pop_ten;
+pub mod five_in_a_row;
+pub mod cylinder;
+pub mod power_up;
+pub mod rotation;
+pub mod script;
+
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// A drop into every column with room, or a placement on every empty cell without gravity
+pub fn normal_moves(game: &Game) -> Vec<Move> {
+    if !game.gravity {
+        return (0..game.columns())
+            .flat_map(|col| (0..game.rows()).map(move |row| (col, row)))
+            .filter(|&(col, row)| game.board[col][row].is_none())
+            .map(|(col, row)| Move::Place(col, row))
+            .collect();
+    }
+
+    (0..game.columns())
+        .filter(|&col| game.free_row(col).is_some())
+        .map(Move::Drop)
+        .collect()
+}
+
+/// Puts a normal coin of the player where a drop or placement lands
+pub fn play_coin(game: &mut Game, player: Player, mv: Move) {
+    let cell = match mv {
+        Move::Drop(col) => game.free_row(col).map(|row| (col, row)),
+        Move::Place(col, row) => Some((col, row)),
+        _ => None,
+    };
+
+    if let Some((col, row)) = cell {
+        game.set_cell(col, row, Some(Piece::coin(player)));
+    }
+}
+
+/// Every player with a line of the variant's length
+pub fn line_winners(game: &Game) -> Vec<Player> {
+    let mut winners = vec![];
+
+    for col in 0..game.columns() as i32 {
+        for row in 0..game.rows() as i32 {
+            let Some(owner) = game.cell(col, row) else { continue; };
+
+            if winners.contains(&owner) {
+                continue;
+            }
+
+            let has_line = DIRECTIONS.iter().any(|(d_col, d_row)| {
+                (1..game.rules().connect()).all(|i| game.cell(col + i * d_col, row + i * d_row) == Some(owner))
+            });
+
+            if has_line {
+                winners.push(owner);
+            }
+        }
+    }
+
+    winners
+}
+
+/// Whether the coin in this cell is part of a line
+pub fn is_in_line(game: &Game, col: i32, row: i32) -> bool {
+    game.cell(col, row).is_some_and(|owner| makes_line(game, col, row, owner))
+}
+
+/// Whether a coin of `owner` in this cell would be part of a line, whatever the cell holds now
+fn makes_line(game: &Game, col: i32, row: i32, owner: Player) -> bool {
+    let connect = game.rules().connect();
+
+    DIRECTIONS.iter().any(|(d_col, d_row)| {
+        let count_from = |sign: i32| {
+            (1..connect)
+                .take_while(|i| game.cell(col + sign * i * d_col, row + sign * i * d_row) == Some(owner))
+                .count() as i32
+        };
+
+        1 + count_from(1) + count_from(-1) >= connect
+    })
+}
+
+/// An empty cell that would complete a line for the player
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct Threat {
+    pub col: usize,
+    pub row: usize,
+    pub player: Player,
+    /// Whether a move can fill the cell right now, with gravity only the lowest empty cell of a column
+    pub immediate: bool,
+}
+
+impl Threat {
+    /// Odd threats are on the first, third and fifth row counted from the bottom, as in Allis'
+    /// analysis. With an even number of cells the first player gets the odd cells in zugzwang,
+    /// the second player the even ones.
+    pub fn is_odd(&self) -> bool {
+        self.row.is_multiple_of(2)
+    }
+}
+
+/// Every empty cell that would complete a line for one of the players still in the game
+pub fn threats(game: &Game) -> Vec<Threat> {
+    let players = game.active_players();
+    let mut threats = vec![];
+
+    for col in 0..game.columns() {
+        for row in 0..game.rows() {
+            if game.board[col][row].is_some() {
+                continue;
+            }
+
+            let immediate = !game.gravity || game.free_row(col) == Some(row);
+
+            for &player in &players {
+                if makes_line(game, col as i32, row as i32, player) {
+                    threats.push(Threat { col, row, player, immediate });
+                }
+            }
+        }
+    }
+
+    threats
+}
+
+/// Scores every window of a line's length that is still open for one player
+pub fn window_score(game: &Game, player: Player) -> i32 {
+    let columns = game.columns() as i32;
+    let rows = game.rows() as i32;
+    let connect = game.rules().connect();
+    let wraps = game.rules().wraps();
+
+    let mut score = 0;
+
+    for col in 0..columns {
+        for row in 0..rows {
+            for (d_col, d_row) in DIRECTIONS {
+                let (end_col, end_row) = (col + (connect - 1) * d_col, row + (connect - 1) * d_row);
+
+                if (end_col >= columns && !wraps) || end_row < 0 || end_row >= rows {
+                    continue;
+                }
+
+                let mut own = 0;
+                let mut other = 0;
+
+                for i in 0..connect {
+                    match game.board[((col + i * d_col) % columns) as usize][(row + i * d_row) as usize] {
+                        // A wall blocks the window for both players
+                        Some(piece) if piece.kind == PieceKind::Wall => {
+                            own += 1;
+                            other += 1;
+                        }
+                        Some(piece) if piece.player == player => own += 1,
+                        Some(_) => other += 1,
+                        None => ()
+                    }
+                }
+
+                score += match (own, other) {
+                    (own, 0) if own == connect - 1 => 50,
+                    (own, 0) if own == connect - 2 => 5,
+                    (0, other) if other == connect - 1 => -50,
+                    (0, other) if other == connect - 2 => -5,
+                    _ => 0
+                };
+            }
+        }
+    }
+
+    score
+}
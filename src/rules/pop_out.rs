@@ -0,0 +1,58 @@
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::player::Player;
+
+use super::{normal_moves, play_coin};
+use super::ruleset::Ruleset;
+
+/// Players may pop one of their own coins out of the bottom row instead of dropping
+pub struct PopOut;
+
+impl Ruleset for PopOut {
+    fn name(&self) -> &str {
+        "PopOut"
+    }
+
+    fn description(&self) -> &str {
+        "Drop or pop your own coins"
+    }
+
+    fn gravity_optional(&self) -> bool {
+        false
+    }
+
+    /// Pops take coins off the board
+    fn one_coin_per_turn(&self) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, game: &Game, player: Player) -> Vec<Move> {
+        let mut moves = normal_moves(game);
+        moves.extend(pops(game, player));
+        moves
+    }
+
+    fn apply(&self, game: &mut Game, player: Player, mv: Move) {
+        match mv {
+            Move::Pop(col) => pop_bottom(game, col),
+            mv => play_coin(game, player, mv),
+        }
+    }
+}
+
+/// A pop of every column with one of the player's coins at the bottom
+pub fn pops(game: &Game, player: Player) -> Vec<Move> {
+    (0..game.columns())
+        .filter(|&col| game.board[col][0].is_some_and(|piece| piece.player == player))
+        .map(Move::Pop)
+        .collect()
+}
+
+/// Removes the bottom coin of the column, the coins above it fall down by one row
+pub fn pop_bottom(game: &mut Game, col: usize) {
+    let mut column = game.board[col].clone();
+    column.remove(0);
+    column.push(None);
+
+    game.set_column(col, column);
+}
@@ -0,0 +1,85 @@
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::game_state::WinReason;
+use crate::player::Player;
+
+use super::pop_out::{pop_bottom, pops};
+use super::ruleset::Ruleset;
+use super::{is_in_line, normal_moves, play_coin, window_score};
+
+const CAPTURES_TO_WIN: u32 = 10;
+
+/// Popping a coin that is part of a line captures it, ten captured coins win.
+/// Any other popped coin is put back on top of a column in the same turn.
+pub struct PopTen;
+
+impl Ruleset for PopTen {
+    fn name(&self) -> &str {
+        "Pop Ten"
+    }
+
+    fn description(&self) -> &str {
+        "Capture ten coins from lines"
+    }
+
+    fn captures_to_win(&self) -> Option<u32> {
+        Some(CAPTURES_TO_WIN)
+    }
+
+    fn gravity_optional(&self) -> bool {
+        false
+    }
+
+    /// Pops take coins off the board
+    fn one_coin_per_turn(&self) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, game: &Game, player: Player) -> Vec<Move> {
+        // A popped coin that wasn't captured goes back on top of a column first
+        if game.holding.is_some() {
+            return normal_moves(game);
+        }
+
+        let mut moves = normal_moves(game);
+        moves.extend(pops(game, player));
+        moves
+    }
+
+    fn apply(&self, game: &mut Game, player: Player, mv: Move) {
+        let Move::Pop(col) = mv else {
+            play_coin(game, player, mv);
+            game.holding = None;
+            return;
+        };
+
+        if is_in_line(game, col as i32, 0) {
+            *game.captured.entry(player).or_insert(0) += 1;
+        } else {
+            game.holding = game.board[col][0];
+        }
+
+        pop_bottom(game, col);
+    }
+
+    /// Lines don't end the game, captures do
+    fn winners(&self, game: &Game) -> (Vec<Player>, WinReason) {
+        let winners = game.players.iter()
+            .filter(|&&player| game.captured(player) >= CAPTURES_TO_WIN)
+            .copied()
+            .collect();
+
+        (winners, WinReason::Captures)
+    }
+
+    /// Open windows, plus the lead in captures over the best rival
+    fn score(&self, game: &Game, player: Player) -> i32 {
+        let rival_captures = game.players.iter()
+            .filter(|&&rival| rival != player)
+            .map(|&rival| game.captured(rival))
+            .max()
+            .unwrap_or(0);
+
+        window_score(game, player) + (game.captured(player) as i32 - rival_captures as i32) * 200
+    }
+}
@@ -0,0 +1,83 @@
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::piece::{Piece, PieceKind};
+use crate::player::Player;
+
+use super::ruleset::Ruleset;
+use super::{normal_moves, play_coin};
+
+/// Each player has one anvil, wall, bomb and x2 coin besides the normal coins
+pub struct PowerUp;
+
+impl Ruleset for PowerUp {
+    fn name(&self) -> &str {
+        "Power Up"
+    }
+
+    fn description(&self) -> &str {
+        "Anvil, wall, bomb and x2 coins"
+    }
+
+    fn powers(&self) -> Vec<PieceKind> {
+        PieceKind::POWERS.to_vec()
+    }
+
+    fn gravity_optional(&self) -> bool {
+        false
+    }
+
+    /// Anvils and bombs take coins off the board, x2 coins give another move
+    fn one_coin_per_turn(&self) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, game: &Game, player: Player) -> Vec<Move> {
+        let mut moves = normal_moves(game);
+
+        for &kind in game.powers(player) {
+            moves.extend((0..game.columns())
+                .filter(|&col| match kind {
+                    PieceKind::Bomb => game.top(col).is_some_and(|piece| piece.player != player),
+                    _ => game.free_row(col).is_some(),
+                })
+                .map(|col| Move::Power(kind, col)));
+        }
+
+        moves
+    }
+
+    fn apply(&self, game: &mut Game, player: Player, mv: Move) {
+        let Move::Power(kind, col) = mv else {
+            play_coin(game, player, mv);
+            return;
+        };
+
+        let free_row = game.free_row(col);
+        let mut column = game.board[col].clone();
+
+        match (kind, free_row) {
+            (PieceKind::Anvil, _) => {
+                column.fill(None);
+                column[0] = Some(Piece::new(player, kind));
+            }
+            (PieceKind::Bomb, _) => {
+                if let Some(target) = column.iter_mut().rev().find(|cell| cell.is_some()) {
+                    *target = None;
+                }
+            }
+            (_, Some(row)) => column[row] = Some(Piece::new(player, kind)),
+            (_, None) => (),
+        }
+
+        game.set_column(col, column);
+
+        if let Some(powers) = game.powers.get_mut(&player) {
+            powers.retain(|&power| power != kind);
+        }
+    }
+
+    /// An x2 coin gives its player another move
+    fn moves_again(&self, mv: Move) -> bool {
+        matches!(mv, Move::Power(PieceKind::Double, _))
+    }
+}
@@ -0,0 +1,88 @@
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::piece::Piece;
+use crate::player::Player;
+
+use super::ruleset::Ruleset;
+use super::{normal_moves, play_coin};
+
+/// A square board that players may turn a quarter instead of dropping, the coins fall to the new bottom
+pub struct Rotation;
+
+impl Ruleset for Rotation {
+    fn name(&self) -> &str {
+        "Rotation"
+    }
+
+    fn description(&self) -> &str {
+        "Turn the board, coins fall anew"
+    }
+
+    /// A turned board has to fit the same frame
+    fn rows(&self, players: usize) -> usize {
+        self.columns(players)
+    }
+
+    fn rotations(&self) -> u32 {
+        3
+    }
+
+    fn gravity_optional(&self) -> bool {
+        false
+    }
+
+    /// Turning the board adds no coin
+    fn one_coin_per_turn(&self) -> bool {
+        false
+    }
+
+    fn legal_moves(&self, game: &Game, player: Player) -> Vec<Move> {
+        let mut moves = normal_moves(game);
+
+        // Turning an empty board changes nothing
+        if game.rotations(player) > 0 && game.board.iter().flatten().any(|cell| cell.is_some()) {
+            moves.extend([Move::RotateLeft, Move::RotateRight]);
+        }
+
+        moves
+    }
+
+    /// Turns the board a quarter, then every coin falls as far as it can
+    fn apply(&self, game: &mut Game, player: Player, mv: Move) {
+        let clockwise = match mv {
+            Move::RotateLeft => false,
+            Move::RotateRight => true,
+            mv => {
+                play_coin(game, player, mv);
+                return;
+            }
+        };
+
+        for (col, mut column) in rotated(&game.board, clockwise).into_iter().enumerate() {
+            let rows = column.len();
+            column.retain(|cell| cell.is_some());
+            column.resize(rows, None);
+
+            game.set_column(col, column);
+        }
+
+        if let Some(rotations) = game.rotations.get_mut(&player) {
+            *rotations -= 1;
+        }
+    }
+}
+
+/// The board turned a quarter, before the coins fall to the new bottom
+pub fn rotated(board: &[Vec<Option<Piece>>], clockwise: bool) -> Vec<Vec<Option<Piece>>> {
+    let (columns, rows) = (board.len(), board[0].len());
+
+    (0..rows)
+        .map(|new_col| (0..columns)
+            .map(|new_row| if clockwise {
+                board[columns - 1 - new_row][new_col]
+            } else {
+                board[new_row][rows - 1 - new_col]
+            })
+            .collect())
+        .collect()
+}
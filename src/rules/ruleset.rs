@@ -0,0 +1,104 @@
+use crate::constants::{COLUMNS, ROWS};
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::game_state::WinReason;
+use crate::piece::{Piece, PieceKind};
+use crate::player::Player;
+
+use super::{line_winners, normal_moves, play_coin, window_score};
+
+/// The rules of a variant. The default methods are the classic rules,
+/// a variant overrides what it changes.
+pub trait Ruleset: Sync {
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// Every player beyond the second adds a column
+    fn columns(&self, players: usize) -> usize {
+        COLUMNS as usize + players.saturating_sub(2)
+    }
+
+    /// Three or more players add a row
+    fn rows(&self, players: usize) -> usize {
+        ROWS as usize + players.saturating_sub(2).min(1)
+    }
+
+    /// The board before the first move, columns left to right and rows bottom up
+    fn initial_board(&self, players: usize) -> Vec<Vec<Option<Piece>>> {
+        vec![vec![None; self.rows(players)]; self.columns(players)]
+    }
+
+    /// The power coins each player starts with
+    fn powers(&self) -> Vec<PieceKind> {
+        vec![]
+    }
+
+    /// How often each player may turn the board
+    fn rotations(&self) -> u32 {
+        0
+    }
+
+    /// How many captured coins win, if coins can be captured
+    fn captures_to_win(&self) -> Option<u32> {
+        None
+    }
+
+    /// How many coins in a row make a line
+    fn connect(&self) -> i32 {
+        4
+    }
+
+    /// Whether the leftmost and rightmost columns are neighbours
+    fn wraps(&self) -> bool {
+        false
+    }
+
+    /// Whether gravity can be turned off, only plain drops can become placements
+    fn gravity_optional(&self) -> bool {
+        true
+    }
+
+    /// Whether every turn adds exactly one coin of the player to the board,
+    /// so the number of coins tells whose turn it is
+    fn one_coin_per_turn(&self) -> bool {
+        true
+    }
+
+    /// Whether every move drops a coin and a line of `connect()` coins wins, the
+    /// classic game on another board, so the solver can play it
+    fn solvable(&self) -> bool {
+        self.one_coin_per_turn()
+    }
+
+    /// Whether the board and the player to move are the whole position, without
+    /// captures, power coins or turns to keep track of, so it can be written down
+    fn board_is_position(&self) -> bool {
+        self.captures_to_win().is_none() && self.powers().is_empty() && self.rotations() == 0
+    }
+
+    /// The moves the player to move may play
+    fn legal_moves(&self, game: &Game, _player: Player) -> Vec<Move> {
+        normal_moves(game)
+    }
+
+    /// Changes the board for a legal move of the player
+    fn apply(&self, game: &mut Game, player: Player, mv: Move) {
+        play_coin(game, player, mv);
+    }
+
+    /// Whether the player moves again right after this move
+    fn moves_again(&self, _mv: Move) -> bool {
+        false
+    }
+
+    /// The players who have reached the goal, and how
+    fn winners(&self, game: &Game) -> (Vec<Player>, WinReason) {
+        (line_winners(game), WinReason::FourInARow)
+    }
+
+    /// How good the position looks for the player, used by the AI
+    fn score(&self, game: &Game, player: Player) -> i32 {
+        window_score(game, player)
+    }
+}
@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rhai::{Array, Engine, Scope, AST};
+
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::game_state::{GameState, WinReason};
+use crate::player::Player;
+
+use super::ruleset::Ruleset;
+use super::{line_winners, normal_moves};
+
+/// Keeps a script stuck in a loop from freezing the game
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+static SCRIPTS: OnceLock<Vec<ScriptRules>> = OnceLock::new();
+
+/// A variant defined by a Rhai script in `assets/variants`.
+///
+/// The script must define `name()` and `description()`, and may define
+/// `columns()`, `rows()` and `connect()` to change the board, `legal_moves(board)`
+/// returning the columns open for a drop and `winners(board)` returning the
+/// colors that have won.
+pub struct ScriptRules {
+    engine: Engine,
+    ast: AST,
+    name: String,
+    description: String,
+    columns: usize,
+    rows: usize,
+    connect: i32,
+    /// Only the first error while playing is reported, the classic rule stands in for the rest
+    failed: AtomicBool,
+}
+
+/// The board as scripts see it, colors are their names and empty cells `""`
+#[derive(Clone)]
+struct ScriptBoard {
+    cells: Vec<Vec<Option<Player>>>,
+    player: String,
+    moves: i64,
+}
+
+impl ScriptBoard {
+    fn new(game: &Game, player: Player) -> Self {
+        let cells = (0..game.columns() as i32)
+            .map(|col| (0..game.rows() as i32).map(|row| game.cell(col, row)).collect())
+            .collect();
+
+        Self { cells, player: String::from(player.text()), moves: game.moves.len() as i64 }
+    }
+
+    fn cell(&self, col: i64, row: i64) -> Option<Player> {
+        let col = usize::try_from(col).ok()?;
+        let row = usize::try_from(row).ok()?;
+
+        *self.cells.get(col)?.get(row)?
+    }
+
+    fn get(&mut self, col: i64, row: i64) -> String {
+        self.cell(col, row).map_or(String::new(), |player| String::from(player.text()))
+    }
+
+    /// The row a coin dropped into the column would land in, -1 for a full column
+    fn free_row(&mut self, col: i64) -> i64 {
+        usize::try_from(col).ok()
+            .and_then(|col| self.cells.get(col))
+            .and_then(|column| column.iter().position(|cell| cell.is_none()))
+            .map_or(-1, |row| row as i64)
+    }
+
+    /// Whether the player has `length` coins in a row anywhere on the board
+    fn has_line(&mut self, player: &str, length: i64) -> bool {
+        let Ok(player) = Player::parse(player) else { return false; };
+
+        (0..self.cells.len() as i64).any(|col| (0..self.cells[0].len() as i64).any(|row| {
+            [(1, 0), (0, 1), (1, 1), (1, -1)].iter().any(|(d_col, d_row)| {
+                (0..length).all(|i| self.cell(col + i * d_col, row + i * d_row) == Some(player))
+            })
+        }))
+    }
+}
+
+impl ScriptRules {
+    fn load(source: &str) -> Result<ScriptRules, String> {
+        let mut engine = Engine::new();
+
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.register_type_with_name::<ScriptBoard>("Board")
+            .register_get("columns", |board: &mut ScriptBoard| board.cells.len() as i64)
+            .register_get("rows", |board: &mut ScriptBoard| board.cells[0].len() as i64)
+            .register_get("player", |board: &mut ScriptBoard| board.player.clone())
+            .register_get("moves", |board: &mut ScriptBoard| board.moves)
+            .register_fn("get", ScriptBoard::get)
+            .register_fn("free_row", ScriptBoard::free_row)
+            .register_fn("has_line", ScriptBoard::has_line);
+
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+
+        let text = |function: &str| engine.call_fn::<String>(&mut Scope::new(), &ast, function, ())
+            .map_err(|e| format!("{function}: {e}"));
+
+        let number = |function: &str, default: i64| {
+            if !ast.iter_functions().any(|f| f.name == function) {
+                return Ok(default);
+            }
+
+            engine.call_fn::<i64>(&mut Scope::new(), &ast, function, ())
+                .map_err(|e| format!("{function}: {e}"))
+        };
+
+        let name = text("name")?;
+        let description = text("description")?;
+        let columns = number("columns", 7)?.clamp(4, 9) as usize;
+        let rows = number("rows", 6)?.clamp(4, 9) as usize;
+        let connect = number("connect", 4)?.clamp(2, 9) as i32;
+
+        Ok(Self { engine, ast, name, description, columns, rows, connect, failed: AtomicBool::new(false) })
+    }
+
+    fn defines(&self, function: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == function)
+    }
+
+    /// Calls the script's function with the board, `None` if it isn't defined or fails
+    fn call(&self, function: &str, game: &Game, player: Player) -> Option<Array> {
+        if !self.defines(function) || self.failed.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let board = ScriptBoard::new(game, player);
+
+        match self.engine.call_fn::<Array>(&mut Scope::new(), &self.ast, function, (board,)) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                if !self.failed.swap(true, Ordering::Relaxed) {
+                    eprintln!("Failed to run {function} of variant '{}': {e}", self.name);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl Ruleset for ScriptRules {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn columns(&self, players: usize) -> usize {
+        self.columns + players.saturating_sub(2)
+    }
+
+    fn rows(&self, players: usize) -> usize {
+        self.rows + players.saturating_sub(2).min(1)
+    }
+
+    fn connect(&self) -> i32 {
+        self.connect
+    }
+
+    /// The script decides on drops, there is nothing to place without gravity
+    fn gravity_optional(&self) -> bool {
+        !self.defines("legal_moves")
+    }
+
+    /// Only scripts that keep the classic moves and lines
+    fn solvable(&self) -> bool {
+        !self.defines("legal_moves") && !self.defines("winners")
+    }
+
+    fn legal_moves(&self, game: &Game, player: Player) -> Vec<Move> {
+        let Some(columns) = self.call("legal_moves", game, player) else { return normal_moves(game); };
+
+        // Full or made up columns are left out
+        columns.into_iter()
+            .filter_map(|col| usize::try_from(col.as_int().ok()?).ok())
+            .filter(|&col| game.free_row(col).is_some())
+            .map(Move::Drop)
+            .collect()
+    }
+
+    fn winners(&self, game: &Game) -> (Vec<Player>, WinReason) {
+        // Lines are checked right after a move, before the turn passes on
+        let player = match game.state {
+            GameState::Running(player) => player,
+            _ => game.players[0],
+        };
+
+        let winners = match self.call("winners", game, player) {
+            Some(winners) => winners.into_iter()
+                .filter_map(|winner| Player::parse(&winner.into_string().ok()?).ok())
+                .filter(|winner| game.players.contains(winner))
+                .collect(),
+            None => line_winners(game),
+        };
+
+        (winners, WinReason::FourInARow)
+    }
+}
+
+/// Loads every `.rhai` script in the folder, scripts with errors are reported and left out
+pub fn load(dir: &Path) {
+    let mut paths: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+        Err(_) => vec![],
+    };
+
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "rhai"));
+    paths.sort();
+
+    let scripts = paths.iter()
+        .filter_map(|path| {
+            let loaded = fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| ScriptRules::load(&source));
+
+            loaded.map_err(|e| eprintln!("Failed to load variant script {}: {}", path.display(), e)).ok()
+        })
+        .collect();
+
+    if SCRIPTS.set(scripts).is_err() {
+        eprintln!("Variant scripts are already loaded");
+    }
+}
+
+/// The loaded scripts, in file name order
+pub fn scripts() -> &'static [ScriptRules] {
+    SCRIPTS.get().map_or(&[], |scripts| scripts)
+}
@@ -0,0 +1,23 @@
+use crate::animation::AnimationStatus;
+use crate::animation::animation_state::AnimationState;
+
+const SCALE_IN_TIME: f64 = 0.25;
+
+/// Grows a placed coin from nothing to full size while fading it in, `progress` runs from 0 to 1
+#[derive(Default)]
+#[derive(Debug)]
+pub struct ScaleInState {
+    pub progress: f64,
+}
+
+impl AnimationState for ScaleInState {
+    fn update(&mut self, dt: f64) -> AnimationStatus {
+        self.progress = (self.progress + dt / SCALE_IN_TIME).min(1.0);
+
+        if self.progress >= 1.0 {
+            return AnimationStatus::Finished;
+        }
+
+        AnimationStatus::Running
+    }
+}
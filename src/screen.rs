@@ -0,0 +1,11 @@
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Screen {
+    Board,
+    MatchSummary,
+    Statistics,
+    Leaderboard,
+    VariantMenu,
+}
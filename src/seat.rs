@@ -0,0 +1,27 @@
+use crate::player::Player;
+
+/// A place at the table, teammates share the color of their coins.
+/// Seats order by member first, so teammates never sit next to each other.
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(PartialOrd)]
+#[derive(Ord)]
+#[derive(Hash)]
+#[derive(Debug)]
+pub struct Seat {
+    pub member: usize,
+    pub player: Player,
+}
+
+impl Seat {
+    pub fn new(player: Player, member: usize) -> Seat {
+        Self { member, player }
+    }
+
+    /// Every player plays alone, one seat per color
+    pub fn solo(players: &[Player]) -> Vec<Seat> {
+        players.iter().map(|&player| Seat::new(player, 0)).collect()
+    }
+}
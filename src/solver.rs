@@ -0,0 +1,520 @@
+use std::cmp::Ordering;
+use std::ops::{BitAnd, BitOr, Not, Shl, Shr};
+
+use crate::game::Game;
+use crate::game_move::Move;
+use crate::game_state::GameState;
+use crate::variant::Variant;
+
+/// Buckets of the transposition table, two entries of 16 bytes each
+const TABLE_BITS: u32 = 23;
+
+/// The longest line the solver looks for
+const MAX_CONNECT: usize = 8;
+
+/// What perfect play from both sides leads to, for the player to move
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Debug)]
+pub enum Value {
+    /// The player to move wins with their nth move from here
+    Win(u32),
+    Draw,
+    /// The opponent wins with their nth move from here
+    Loss(u32),
+}
+
+impl Value {
+    pub fn text(&self) -> String {
+        match self {
+            Value::Win(moves) => format!("win {moves}"),
+            Value::Draw => String::from("draw"),
+            Value::Loss(moves) => format!("loss {moves}"),
+        }
+    }
+
+    /// Parses `win <moves>`, `draw` or `loss <moves>`
+    pub fn parse(text: &str) -> Result<Value, String> {
+        let moves = |count: &str| count.parse::<u32>().ok()
+            .filter(|&count| count > 0)
+            .ok_or(format!("Invalid value '{text}'"));
+
+        match text.split_once(' ') {
+            Some(("win", count)) => Ok(Value::Win(moves(count)?)),
+            Some(("loss", count)) => Ok(Value::Loss(moves(count)?)),
+            None if text == "draw" => Ok(Value::Draw),
+            _ => Err(format!("Invalid value '{text}'")),
+        }
+    }
+}
+
+/// A board as two bitboards. Each column takes the bits of its rows bottom up
+/// and one more that stays empty, so lines can't run over into the next column.
+#[derive(Clone)]
+#[derive(Copy)]
+struct Position {
+    /// The coins of the player to move
+    current: u64,
+    /// Every coin on the board
+    mask: u64,
+    coins: i32,
+}
+
+impl Position {
+    /// The position after the player to move drops a coin on the cell of `bit`
+    fn play(&self, bit: u64) -> Position {
+        Position { current: self.current ^ self.mask, mask: self.mask | bit, coins: self.coins + 1 }
+    }
+
+    /// Sets the bit above the top coin of every column, which tells the positions apart
+    fn key(&self) -> u64 {
+        self.current + self.mask
+    }
+}
+
+/// The bounds of the score of a position found so far
+#[derive(Clone)]
+#[derive(Copy)]
+struct Entry {
+    key: u64,
+    /// Fewer coins usually took longer to search
+    coins: u8,
+    lower: i8,
+    upper: i8,
+}
+
+const EMPTY_ENTRY: Entry = Entry { key: 0, coins: u8::MAX, lower: i8::MIN, upper: i8::MAX };
+
+/// Solves positions of two player games whose moves are drops and whose goal is a
+/// line, so every position has a value under perfect play. It is a negamax search
+/// over bitboards with null windows: each search only tells whether the score is
+/// above a guess, and the guesses narrow down on the score.
+///
+/// Scores count the empty cells left when the game ends, positive when the player
+/// to move wins. A quicker win scores higher, a draw scores 0.
+pub struct Solver {
+    variant: Variant,
+    columns: usize,
+    rows: usize,
+    /// Bits per column, one more than the rows
+    height: usize,
+    wraps: bool,
+    cells: i32,
+    /// The bottom cell of every column
+    bottom: u64,
+    /// Every cell of the board
+    board: u64,
+    connect: usize,
+    /// Columns from the centre outwards, the best moves are usually near the centre
+    order: Vec<usize>,
+    /// The shift from a cell to the next one of a line, up, right and along both diagonals
+    directions: [u32; 4],
+    /// Kept between positions, the bounds hold for whatever is solved next. The first
+    /// entry of a bucket keeps the position with the fewest coins, the second the latest.
+    table: Vec<[Entry; 2]>,
+}
+
+impl Solver {
+    /// A solver for positions of the game's variant and board, if it has one
+    pub fn new(game: &Game) -> Option<Solver> {
+        let (columns, rows, connect) = (game.columns(), game.rows(), game.rules().connect());
+        let height = rows + 1;
+
+        // A line may not come round to its own cells on a cylinder
+        if !game.rules().solvable() || columns * height > 64 || connect as usize > MAX_CONNECT
+            || (game.rules().wraps() && connect as usize > columns) {
+            return None;
+        }
+
+        let bottom = (0..columns).fold(0, |bottom, col| bottom | 1 << (col * height));
+
+        let mut order: Vec<usize> = (0..columns).collect();
+        order.sort_by_key(|&col| (2 * col as i32 + 1 - columns as i32).abs());
+
+        Some(Solver {
+            variant: game.variant,
+            columns,
+            rows,
+            height,
+            wraps: game.rules().wraps(),
+            cells: (columns * rows) as i32,
+            bottom,
+            board: bottom * ((1 << rows) - 1),
+            connect: connect as usize,
+            order,
+            directions: [1, height as u32, height as u32 - 1, height as u32 + 1],
+            table: vec![[EMPTY_ENTRY; 2]; 1 << TABLE_BITS],
+        })
+    }
+
+    /// The best move of the player to move and the value of the position, if the
+    /// game is running with two players and gravity on the solver's board
+    pub fn solve(&mut self, game: &Game) -> Option<(Move, Value)> {
+        let position = self.position(game)?;
+        let score = self.score(position);
+
+        let col = self.order.clone().into_iter().find(|&col| self.reaches(position, col, score))?;
+
+        Some((Move::Drop(col), self.value(position, score)))
+    }
+
+    /// The value of the position after the move, for the player making it
+    pub fn move_value(&mut self, game: &Game, mv: Move) -> Option<Value> {
+        let Move::Drop(col) = mv else { return None; };
+        let position = self.position(game)?;
+        let bit = self.possible(position.mask) & self.column(col);
+
+        if bit == 0 {
+            return None;
+        }
+
+        let score = if self.winning_cells(position.current, position.mask) & bit != 0 {
+            (self.cells + 1 - position.coins) / 2
+        } else {
+            -self.score(position.play(bit))
+        };
+
+        Some(self.value(position, score))
+    }
+
+    fn position(&self, game: &Game) -> Option<Position> {
+        let GameState::Running(player) = game.state else { return None; };
+
+        if game.variant != self.variant || (game.columns(), game.rows()) != (self.columns, self.rows)
+            || game.active_players().len() != 2 || !game.gravity {
+            return None;
+        }
+
+        let mut position = Position { current: 0, mask: 0, coins: 0 };
+
+        for (col, column) in game.board.iter().enumerate() {
+            for (row, cell) in column.iter().enumerate() {
+                let Some(piece) = cell else { continue; };
+                let bit = 1 << (col * self.height + row);
+
+                position.mask |= bit;
+                position.coins += 1;
+
+                if piece.player == player {
+                    position.current |= bit;
+                }
+            }
+        }
+
+        Some(position)
+    }
+
+    /// The exact score, narrowed down with null window searches
+    fn score(&mut self, position: Position) -> i32 {
+        let mut min = -(self.cells - position.coins) / 2;
+        let mut max = (self.cells + 1 - position.coins) / 2;
+
+        while min < max {
+            // Guesses near 0 first, most positions are close to a draw
+            let mut guess = min + (max - min) / 2;
+
+            if guess <= 0 && min / 2 < guess {
+                guess = min / 2;
+            } else if guess >= 0 && max / 2 > guess {
+                guess = max / 2;
+            }
+
+            let score = self.search(position, guess, guess + 1);
+
+            if score <= guess {
+                max = score;
+            } else {
+                min = score;
+            }
+        }
+
+        min
+    }
+
+    /// Whether dropping in the column keeps the score
+    fn reaches(&mut self, position: Position, col: usize, score: i32) -> bool {
+        let bit = self.possible(position.mask) & self.column(col);
+
+        if bit == 0 {
+            return false;
+        }
+
+        if self.winning_cells(position.current, position.mask) & bit != 0 {
+            return true;
+        }
+
+        -self.search(position.play(bit), -score, -score + 1) >= score
+    }
+
+    /// A score within the window, or a bound beyond it
+    fn search(&mut self, position: Position, alpha: i32, beta: i32) -> i32 {
+        if self.winning_cells(position.current, position.mask) & self.possible(position.mask) != 0 {
+            return (self.cells + 1 - position.coins) / 2;
+        }
+
+        self.negamax(position, alpha, beta)
+    }
+
+    /// The search proper, for a player who can't win with their next move
+    fn negamax(&mut self, position: Position, mut alpha: i32, mut beta: i32) -> i32 {
+        let moves = self.non_losing_moves(position);
+
+        if moves == 0 {
+            return -(self.cells - position.coins) / 2;
+        }
+
+        // Neither player can win with the last two cells
+        if position.coins >= self.cells - 2 {
+            return 0;
+        }
+
+        // The opponent can't win right away, we can't win before our next move
+        let min = -(self.cells - 2 - position.coins) / 2;
+        let max = (self.cells - 1 - position.coins) / 2;
+
+        alpha = alpha.max(min);
+        beta = beta.min(max);
+
+        // A position and its mirror image share an entry
+        let key = position.key().min(self.mirrored(position.key()));
+
+        if let Some(entry) = self.table[Solver::index(key)].iter().find(|entry| entry.key == key) {
+            alpha = alpha.max(entry.lower as i32);
+            beta = beta.min(entry.upper as i32);
+        }
+
+        if alpha >= beta {
+            return alpha;
+        }
+
+        // Moves that make the most lines of three are tried first
+        let mut sorted = [(0, 0); 32];
+        let mut count = 0;
+
+        for &col in self.order.iter() {
+            let bit = moves & self.column(col);
+
+            if bit == 0 {
+                continue;
+            }
+
+            let threats = self.winning_cells(position.current | bit, position.mask | bit).count_ones();
+            let mut i = count;
+
+            while i > 0 && sorted[i - 1].0 < threats {
+                sorted[i] = sorted[i - 1];
+                i -= 1;
+            }
+
+            sorted[i] = (threats, bit);
+            count += 1;
+        }
+
+        for &(_, bit) in &sorted[..count] {
+            let score = -self.negamax(position.play(bit), -beta, -alpha);
+
+            if score >= beta {
+                self.store(key, position.coins, score, i32::MAX);
+                return score;
+            }
+
+            alpha = alpha.max(score);
+        }
+
+        self.store(key, position.coins, i32::MIN, alpha);
+
+        alpha
+    }
+
+    fn index(key: u64) -> usize {
+        (key.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (64 - TABLE_BITS)) as usize
+    }
+
+    fn store(&mut self, key: u64, coins: i32, lower: i32, upper: i32) {
+        let bucket = &mut self.table[Solver::index(key)];
+
+        let slot = match bucket.iter().position(|entry| entry.key == key) {
+            Some(slot) => slot,
+            None if coins <= bucket[0].coins as i32 => {
+                bucket[1] = bucket[0];
+                bucket[0] = Entry { key, coins: coins as u8, ..EMPTY_ENTRY };
+                0
+            }
+            None => {
+                bucket[1] = Entry { key, coins: coins as u8, ..EMPTY_ENTRY };
+                1
+            }
+        };
+
+        let entry = &mut bucket[slot];
+
+        entry.lower = entry.lower.max(lower.clamp(i8::MIN as i32, i8::MAX as i32) as i8);
+        entry.upper = entry.upper.min(upper.clamp(i8::MIN as i32, i8::MAX as i32) as i8);
+    }
+
+    fn value(&self, position: Position, score: i32) -> Value {
+        match score.cmp(&0) {
+            Ordering::Greater => Value::Win(((self.cells + 1 - position.coins) / 2 - score + 1) as u32),
+            Ordering::Equal => Value::Draw,
+            Ordering::Less => Value::Loss(((self.cells - position.coins) / 2 + score + 1) as u32),
+        }
+    }
+
+    /// The bits with the columns in reverse order, the bits above the rows as well
+    fn mirrored(&self, bits: u64) -> u64 {
+        (0..self.columns).fold(0, |mirrored, col| {
+            let column = bits >> (col * self.height) & ((1 << self.height) - 1);
+            mirrored | column << ((self.columns - 1 - col) * self.height)
+        })
+    }
+
+    fn column(&self, col: usize) -> u64 {
+        ((1 << self.rows) - 1) << (col * self.height)
+    }
+
+    /// The lowest empty cell of every column that isn't full
+    fn possible(&self, mask: u64) -> u64 {
+        (mask + self.bottom) & self.board
+    }
+
+    /// The moves that don't let the opponent win right away, none if they can't be stopped
+    fn non_losing_moves(&self, position: Position) -> u64 {
+        let possible = self.possible(position.mask);
+        let threats = self.winning_cells(position.current ^ position.mask, position.mask);
+        let forced = possible & threats;
+
+        let possible = match forced.count_ones() {
+            0 => possible,
+            1 => forced,
+            _ => return 0,
+        };
+
+        // Nor fill the cell below one of their lines
+        possible & !(threats >> 1)
+    }
+
+    /// The empty cells that would complete a line of the coins
+    fn winning_cells(&self, coins: u64, mask: u64) -> u64 {
+        let cells = if self.wraps {
+            // On a cylinder the board is laid out twice, a line over the edge is whole across the copies
+            let width = (self.columns * self.height) as u32;
+            let cells = line_ends(coins as u128 | (coins as u128) << width, self.connect, &self.directions);
+
+            (cells | cells >> width) as u64
+        } else {
+            line_ends(coins, self.connect, &self.directions)
+        };
+
+        cells & self.board & !mask
+    }
+}
+
+/// The cells with the rest of a line of coins on either side of them, in any direction
+fn line_ends<T>(coins: T, connect: usize, directions: &[u32]) -> T
+where
+    T: Copy + Default + Not<Output = T> + BitAnd<Output = T> + BitOr<Output = T> + Shl<u32, Output = T> + Shr<u32, Output = T>,
+{
+    let mut cells = T::default();
+
+    for &direction in directions {
+        // The cells with at least i coins in a row right after them
+        let mut after = [!T::default(); MAX_CONNECT];
+
+        for i in 1..connect {
+            after[i] = after[i - 1] & coins >> (i as u32 * direction);
+        }
+
+        // Grown along with the coins before the cell, the rest have to come after it
+        let mut before = !T::default();
+
+        for i in 0..connect {
+            if i > 0 {
+                before = before & coins << (i as u32 * direction);
+            }
+
+            cells = cells | before & after[connect - 1 - i];
+        }
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::player::Player;
+    use crate::seat::Seat;
+
+    const POSITIONS: usize = 20;
+
+    /// The score by trying every move to the end of the game
+    fn full_search(game: &mut Game, cells: i32) -> i32 {
+        let coins = game.board.iter().flatten().flatten().count() as i32;
+        let mut best = i32::MIN;
+
+        for mv in game.legal_moves() {
+            game.apply(mv);
+
+            let score = match game.state {
+                GameState::Win { .. } => (cells + 1 - coins) / 2,
+                GameState::Draw(_) => 0,
+                _ => -full_search(game, cells),
+            };
+
+            game.undo();
+            best = best.max(score);
+        }
+
+        best
+    }
+
+    #[test]
+    fn solves_late_positions_like_a_full_search() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for variant in [Variant::Classic, Variant::Cylinder, Variant::FiveInARow] {
+            let initial = Game::initial(variant, Seat::solo(&[Player::Yellow, Player::Red]));
+            let mut solver = Solver::new(&initial).unwrap();
+            let mut solved = 0;
+
+            while solved < POSITIONS {
+                // Random moves until eight cells are left, games that end before are left out
+                let mut game = initial.clone();
+                game.start(Player::Yellow);
+
+                while game.board.iter().flatten().flatten().count() < solver.cells as usize - 8 {
+                    let Some(&mv) = game.legal_moves().choose(&mut rng) else { break; };
+                    game.apply(mv);
+
+                    if !matches!(game.state, GameState::Running(_)) {
+                        break;
+                    }
+                }
+
+                let Some(position) = solver.position(&game) else { continue; };
+                let expected = solver.value(position, full_search(&mut game.clone(), solver.cells));
+                let (mv, value) = solver.solve(&game).unwrap();
+
+                assert_eq!(value, expected, "{variant:?} {}", Move::list_text(&game.moves));
+                assert_eq!(solver.move_value(&game, mv), Some(value), "{variant:?} {}", Move::list_text(&game.moves));
+                solved += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn values_read_back() {
+        for value in [Value::Win(3), Value::Draw, Value::Loss(1)] {
+            assert_eq!(Value::parse(&value.text()), Ok(value));
+        }
+
+        assert!(Value::parse("win 0").is_err());
+        assert!(Value::parse("loss").is_err());
+    }
+}
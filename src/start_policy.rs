@@ -0,0 +1,59 @@
+use crate::player::Player;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum StartPolicy {
+    Yellow,
+    Red,
+    Alternate,
+    Random,
+    LoserStarts,
+}
+
+impl StartPolicy {
+    pub const ALL: [StartPolicy; 5] = [
+        StartPolicy::Yellow,
+        StartPolicy::Red,
+        StartPolicy::Alternate,
+        StartPolicy::Random,
+        StartPolicy::LoserStarts,
+    ];
+
+    /// Picks the starting player from who started the previous game and who won it.
+    /// With more than two players the one seated after the winner counts as the loser.
+    pub fn first(&self, previous: Option<(Player, Option<Player>)>, players: &[Player]) -> Player {
+        match (self, previous) {
+            (StartPolicy::Yellow, _) => Player::Yellow,
+            (StartPolicy::Red, _) => Player::Red,
+            (StartPolicy::Random, _) => players[rand::random::<usize>() % players.len()],
+            (StartPolicy::LoserStarts, Some((_, Some(winner)))) => winner.next_in(players),
+            // Draws fall back to alternating
+            (StartPolicy::Alternate | StartPolicy::LoserStarts, Some((first, _))) => first.next_in(players),
+            (StartPolicy::Alternate | StartPolicy::LoserStarts, None) => Player::Yellow,
+        }
+    }
+
+    pub fn next(&self) -> StartPolicy {
+        let i = Self::ALL.iter().position(|policy| policy == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            StartPolicy::Yellow => "yellow",
+            StartPolicy::Red => "red",
+            StartPolicy::Alternate => "alternate",
+            StartPolicy::Random => "random",
+            StartPolicy::LoserStarts => "loser",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<StartPolicy, String> {
+        Self::ALL.iter()
+            .find(|policy| policy.text() == text)
+            .copied()
+            .ok_or(format!("Unknown starting player option '{text}'"))
+    }
+}
@@ -0,0 +1,73 @@
+use crate::rules::classic::Classic;
+use crate::rules::cylinder::Cylinder;
+use crate::rules::five_in_a_row::FiveInARow;
+use crate::rules::pop_out::PopOut;
+use crate::rules::pop_ten::PopTen;
+use crate::rules::power_up::PowerUp;
+use crate::rules::rotation::Rotation;
+use crate::rules::ruleset::Ruleset;
+use crate::rules::script;
+
+/// Names a variant in the menu and in the game records, its rules live in `rules`
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Variant {
+    Classic,
+    PopOut,
+    PopTen,
+    FiveInARow,
+    Cylinder,
+    PowerUp,
+    Rotation,
+    /// One of the scripts loaded from `assets/variants`
+    Script(usize),
+}
+
+impl Variant {
+    pub const ALL: [Variant; 7] = [
+        Variant::Classic,
+        Variant::PopOut,
+        Variant::PopTen,
+        Variant::FiveInARow,
+        Variant::Cylinder,
+        Variant::PowerUp,
+        Variant::Rotation,
+    ];
+
+    /// The built-in variants followed by the loaded scripts
+    pub fn all() -> Vec<Variant> {
+        let scripts = (0..script::scripts().len()).map(Variant::Script);
+
+        Self::ALL.into_iter().chain(scripts).collect()
+    }
+
+    /// The rules of the variant
+    pub fn rules(&self) -> &'static dyn Ruleset {
+        match self {
+            Variant::Classic => &Classic,
+            Variant::PopOut => &PopOut,
+            Variant::PopTen => &PopTen,
+            Variant::FiveInARow => &FiveInARow,
+            Variant::Cylinder => &Cylinder,
+            Variant::PowerUp => &PowerUp,
+            Variant::Rotation => &Rotation,
+            Variant::Script(i) => &script::scripts()[*i],
+        }
+    }
+
+    pub fn text(&self) -> &'static str {
+        self.rules().name()
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.rules().description()
+    }
+
+    pub fn parse(text: &str) -> Result<Variant, String> {
+        Self::all().into_iter()
+            .find(|variant| variant.text() == text)
+            .ok_or(format!("Unknown variant '{text}'"))
+    }
+}
@@ -0,0 +1,84 @@
+use crate::piece::{Piece, PieceKind};
+use crate::player::Player;
+
+/// Rows a board can have at most, so that every cell gets keys of its own
+const MAX_ROWS: u64 = 16;
+
+/// Offsets that keep the keys of the rest of the position apart from those of the cells
+const SIDE_TO_MOVE: u64 = 1 << 32;
+const CAPTURED: u64 = 2 << 32;
+const POWERS: u64 = 3 << 32;
+const ROTATIONS: u64 = 4 << 32;
+const HOLDING: u64 = 5 << 32;
+
+/// The key of a number, the same on every machine so hashes can be compared
+/// between games. This is the SplitMix64 finalizer.
+fn key(index: u64) -> u64 {
+    let mut x = index.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn player_index(player: Player) -> u64 {
+    Player::ALL.iter().position(|&other| other == player).unwrap_or(0) as u64
+}
+
+fn kind_index(kind: PieceKind) -> u64 {
+    match kind {
+        PieceKind::Normal => 0,
+        PieceKind::Anvil => 1,
+        PieceKind::Wall => 2,
+        PieceKind::Bomb => 3,
+        PieceKind::Double => 4,
+    }
+}
+
+/// The key of the piece on the cell
+pub fn piece(col: usize, row: usize, piece: Piece) -> u64 {
+    let cell = col as u64 * MAX_ROWS + row as u64;
+
+    key((cell * 4 + player_index(piece.player)) * 8 + kind_index(piece.kind))
+}
+
+/// The hash of the board and of its mirror image, from scratch
+pub fn board(board: &[Vec<Option<Piece>>]) -> (u64, u64) {
+    let columns = board.len();
+    let mut hash = 0;
+    let mut mirror_hash = 0;
+
+    for (col, column) in board.iter().enumerate() {
+        for (row, cell) in column.iter().enumerate() {
+            if let Some(cell) = *cell {
+                hash ^= self::piece(col, row, cell);
+                mirror_hash ^= self::piece(columns - 1 - col, row, cell);
+            }
+        }
+    }
+
+    (hash, mirror_hash)
+}
+
+pub fn side_to_move(player: Player) -> u64 {
+    key(SIDE_TO_MOVE + player_index(player))
+}
+
+/// The key of the player's captured coins
+pub fn captured(player: Player, count: u32) -> u64 {
+    key(CAPTURED + player_index(player) * 256 + count as u64)
+}
+
+/// The key of a power coin the player has left
+pub fn power(player: Player, kind: PieceKind) -> u64 {
+    key(POWERS + player_index(player) * 8 + kind_index(kind))
+}
+
+/// The key of the player's rotations left
+pub fn rotations(player: Player, count: u32) -> u64 {
+    key(ROTATIONS + player_index(player) * 256 + count as u64)
+}
+
+/// The key of a popped coin of the player that has to be put back
+pub fn holding(player: Player) -> u64 {
+    key(HOLDING + player_index(player))
+}